@@ -0,0 +1,252 @@
+#[macro_use]
+extern crate criterion;
+extern crate failure;
+extern crate lime_render as render;
+extern crate shrev;
+extern crate specs;
+#[macro_use]
+extern crate vulkano;
+#[macro_use]
+extern crate vulkano_shader_derive;
+extern crate winit;
+
+use std::sync::Arc;
+
+use criterion::Criterion;
+use failure::Fallible;
+use render::{d2, testing, Color, Frame, ImageTarget, Layer, LayerId, Layers, Stage};
+use shrev::EventChannel;
+use specs::prelude::*;
+use vulkano::buffer::CpuBufferPool;
+use vulkano::command_buffer::AutoCommandBufferBuilder;
+use vulkano::descriptor::descriptor_set::FixedSizeDescriptorSetsPool;
+use vulkano::descriptor::PipelineLayoutAbstract;
+use vulkano::device::Device;
+use vulkano::framebuffer::{RenderPassAbstract, Subpass};
+use vulkano::pipeline::vertex::SingleBufferDefinition;
+use vulkano::pipeline::GraphicsPipeline;
+
+const DIMENSIONS: [u32; 2] = [1024, 768];
+const RECTS: usize = 10_000;
+
+/// How the rectangles are drawn.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Path {
+    /// `d2::Renderer::draw_rect`, with four indexed vertices per rectangle.
+    Rects,
+    /// `d2::Renderer::draw_tris`, with six vertices per rectangle.
+    Tris,
+    /// `PoolLayer`, with six vertices per rectangle.
+    Baseline,
+}
+
+/// Draws a grid of small rectangles every frame.
+struct Rects {
+    path: Path,
+    baseline: Option<LayerId>,
+}
+
+impl Rects {
+    fn new(path: Path) -> Self {
+        Rects {
+            path,
+            baseline: None,
+        }
+    }
+}
+
+impl<'a> System<'a> for Rects {
+    type SystemData = (WriteExpect<'a, d2::Renderer>, WriteExpect<'a, Layers>);
+
+    fn run(&mut self, (mut renderer, mut layers): Self::SystemData) {
+        let mut baseline = match self.path {
+            Path::Baseline => {
+                let id = match self.baseline {
+                    Some(id) => id,
+                    None => layers.add(Stage::Overlay, 0, PoolLayer::default()).unwrap(),
+                };
+                self.baseline = Some(id);
+                layers.get_mut::<PoolLayer>(id)
+            }
+            Path::Rects | Path::Tris => None,
+        };
+
+        let columns = 100;
+        for i in 0..RECTS {
+            let x = (i % columns) as f32 * 10.0;
+            let y = (i / columns) as f32 * 7.0;
+            let (min, max) = (d2::Point(x, y), d2::Point(x + 8.0, y + 5.0));
+            let color = Color::hsv((i % 360) as f32, 1.0, 1.0);
+            match baseline {
+                Some(ref mut layer) => layer.queue(&rect_tris(min, max), color),
+                None if self.path == Path::Rects => renderer.draw_rect(min, max, color),
+                None => renderer.draw_tris(&rect_tris(min, max), color),
+            }
+        }
+    }
+}
+
+fn rect_tris(min: d2::Point, max: d2::Point) -> [d2::Point; 6] {
+    [
+        d2::Point(min.0, max.1),
+        min,
+        max,
+        max,
+        min,
+        d2::Point(max.0, min.1),
+    ]
+}
+
+type Pipeline = Arc<
+    GraphicsPipeline<
+        SingleBufferDefinition<Vertex>,
+        Box<PipelineLayoutAbstract + Send + Sync>,
+        Arc<RenderPassAbstract + Send + Sync>,
+    >,
+>;
+
+/// The triangle path `d2::Renderer` used before its ring buffers, kept as a
+/// baseline. Each frame the queued vertices are copied into a new
+/// `CpuBufferPool` chunk and drawn without indices.
+#[derive(Default)]
+struct PoolLayer {
+    resources: Option<PoolResources>,
+    queued: Vec<Vertex>,
+}
+
+struct PoolResources {
+    vbuf: CpuBufferPool<Vertex>,
+    ubuf: CpuBufferPool<vs::ty::Data>,
+    pipe: Pipeline,
+    pool: FixedSizeDescriptorSetsPool<Pipeline>,
+}
+
+impl PoolLayer {
+    fn queue(&mut self, vertices: &[d2::Point], color: Color) {
+        self.queued
+            .extend(vertices.iter().map(|&position| Vertex { position, color }));
+    }
+}
+
+impl Layer for PoolLayer {
+    fn name(&self) -> &str {
+        "baseline"
+    }
+
+    fn build(
+        &mut self,
+        device: &Arc<Device>,
+        subpass: Subpass<Arc<RenderPassAbstract + Send + Sync>>,
+    ) -> Fallible<()> {
+        let vs = vs::Shader::load(Arc::clone(device))?;
+        let fs = fs::Shader::load(Arc::clone(device))?;
+        let pipe: Pipeline = Arc::new(
+            GraphicsPipeline::start()
+                .vertex_input_single_buffer::<Vertex>()
+                .vertex_shader(vs.main_entry_point(), ())
+                .triangle_list()
+                .viewports_dynamic_scissors_irrelevant(1)
+                .fragment_shader(fs.main_entry_point(), ())
+                .render_pass(subpass)
+                .build(Arc::clone(device))?,
+        );
+
+        self.resources = Some(PoolResources {
+            vbuf: CpuBufferPool::vertex_buffer(Arc::clone(device)),
+            ubuf: CpuBufferPool::uniform_buffer(Arc::clone(device)),
+            pool: FixedSizeDescriptorSetsPool::new(Arc::clone(&pipe), 0),
+            pipe,
+        });
+        Ok(())
+    }
+
+    fn draw(
+        &mut self,
+        cmd: AutoCommandBufferBuilder,
+        frame: &Frame,
+    ) -> Fallible<AutoCommandBufferBuilder> {
+        let res = self.resources.as_mut().expect("layer was not built");
+        if self.queued.is_empty() {
+            return Ok(cmd);
+        }
+
+        let vbuf = res.vbuf.chunk(self.queued.drain(..))?;
+        let ubuf = res.ubuf.next(vs::ty::Data {
+            dimensions: frame.logical_size,
+        })?;
+        let set = res.pool.next().add_buffer(ubuf)?.build()?;
+        Ok(cmd.draw(Arc::clone(&res.pipe), frame.state, vbuf, set, ())?)
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+struct Vertex {
+    position: d2::Point,
+    color: Color,
+}
+
+impl_vertex!(Vertex, position, color);
+
+#[allow(unused)]
+mod vs {
+    #[derive(VulkanoShader)]
+    #[ty = "vertex"]
+    #[path = "shader/d2/vert.glsl"]
+    struct Dummy;
+}
+
+#[allow(unused)]
+mod fs {
+    #[derive(VulkanoShader)]
+    #[ty = "fragment"]
+    #[path = "shader/d2/frag.glsl"]
+    struct Dummy;
+}
+
+/// Render whole frames, including submission and readback of the target.
+fn bench_frame(c: &mut Criterion, name: &str, path: Path) {
+    let mut world = World::new();
+    let mut dispatcher = DispatcherBuilder::new().with(Rects::new(path), "Rects", &[]);
+    world.add_resource(EventChannel::<winit::Event>::new());
+    render::init::<ImageTarget>(&mut world, &mut dispatcher, DIMENSIONS, &["Rects"]).unwrap();
+    let mut dispatcher = dispatcher.build();
+
+    c.bench_function(name, move |b| {
+        b.iter(|| {
+            dispatcher.run_now(&mut world.res);
+            world.maintain();
+        })
+    });
+}
+
+/// Queue the rectangles and record the commands of a frame, without submitting
+/// them.
+fn bench_record(c: &mut Criterion, name: &str, path: Path) {
+    let mut world = World::new();
+    world.add_resource(EventChannel::<winit::Event>::new());
+    render::init::<ImageTarget>(&mut world, &mut DispatcherBuilder::new(), DIMENSIONS, &[])
+        .unwrap();
+    let mut rects = Rects::new(path);
+
+    c.bench_function(name, move |b| {
+        b.iter(|| {
+            rects.run_now(&world.res);
+            testing::record_frame(&world).unwrap();
+        })
+    });
+}
+
+fn frame(c: &mut Criterion) {
+    bench_frame(c, "frame draw_rect 10k", Path::Rects);
+    bench_frame(c, "frame draw_tris 10k rects", Path::Tris);
+    bench_frame(c, "frame baseline 10k rects", Path::Baseline);
+}
+
+fn record(c: &mut Criterion) {
+    bench_record(c, "record draw_rect 10k", Path::Rects);
+    bench_record(c, "record draw_tris 10k rects", Path::Tris);
+    bench_record(c, "record baseline 10k rects", Path::Baseline);
+}
+
+criterion_group!(benches, frame, record);
+criterion_main!(benches);
@@ -1,6 +1,10 @@
 fn main() {
-    println!("cargo:rerun-if-changed=shaders/d2/frag.glsl");
-    println!("cargo:rerun-if-changed=shaders/d2/vert.glsl");
-    println!("cargo:rerun-if-changed=shaders/d3/frag.glsl");
-    println!("cargo:rerun-if-changed=shaders/d3/vert.glsl");
+    println!("cargo:rerun-if-changed=shader/d2/frag.glsl");
+    println!("cargo:rerun-if-changed=shader/d2/vert.glsl");
+    println!("cargo:rerun-if-changed=shader/d3/debug/frag.glsl");
+    println!("cargo:rerun-if-changed=shader/d3/debug/vert.glsl");
+    println!("cargo:rerun-if-changed=shader/d3/frag.glsl");
+    println!("cargo:rerun-if-changed=shader/d3/vert.glsl");
+    println!("cargo:rerun-if-changed=shader/post/frag.glsl");
+    println!("cargo:rerun-if-changed=shader/post/vert.glsl");
 }
@@ -44,7 +44,8 @@ fn main() {
         .with(D3, "D3", &[])
         .with(D2, "D2", &[]);
     world.add_resource(EventChannel::<winit::Event>::new());
-    render::init::<ImageTarget>(&mut world, &mut dispatcher, [500, 500], "D3", "D2");
+    render::init::<ImageTarget>(&mut world, &mut dispatcher, [500, 500], &["D3", "D2"])
+        .unwrap();
     let mut dispatcher = dispatcher.build();
 
     dispatcher.run_now(&mut world.res);
@@ -7,7 +7,9 @@ extern crate winit;
 use render::{d2, Color, SwapchainTarget};
 use shrev::EventChannel;
 use specs::prelude::*;
-use winit::{Event, EventsLoop, WindowBuilder, WindowEvent};
+use winit::{
+    ElementState, Event, EventsLoop, KeyboardInput, VirtualKeyCode, WindowBuilder, WindowEvent,
+};
 
 struct D3;
 
@@ -42,7 +44,7 @@ fn main() {
         .with(D3, "D3", &[])
         .with(D2, "D2", &[]);
     world.add_resource(EventChannel::<winit::Event>::new());
-    render::init::<SwapchainTarget>(&mut world, &mut dispatcher, window, "D3", "D2");
+    render::init::<SwapchainTarget>(&mut world, &mut dispatcher, window, &["D3", "D2"]).unwrap();
     let mut dispatcher = dispatcher.build();
 
     let mut quit = false;
@@ -53,6 +55,21 @@ fn main() {
                     event: WindowEvent::CloseRequested,
                     ..
                 } => quit = true,
+                Event::WindowEvent {
+                    event:
+                        WindowEvent::KeyboardInput {
+                            input:
+                                KeyboardInput {
+                                    state: ElementState::Pressed,
+                                    virtual_keycode: Some(VirtualKeyCode::F12),
+                                    ..
+                                },
+                            ..
+                        },
+                    ..
+                } => world
+                    .write_resource::<SwapchainTarget>()
+                    .capture_next_frame(),
                 event => world
                     .write_resource::<EventChannel<Event>>()
                     .single_write(event),
@@ -61,5 +78,13 @@ fn main() {
 
         dispatcher.run_now(&mut world.res);
         world.maintain();
+
+        if let Some(screenshot) = world
+            .write_resource::<SwapchainTarget>()
+            .take_screenshot()
+            .unwrap()
+        {
+            screenshot.save_png("screenshot.png").unwrap();
+        }
     }
 }
@@ -0,0 +1,15 @@
+#version 450
+
+layout(location = 0) in vec3 position;
+layout(location = 1) in vec4 color;
+
+layout(location = 0) out vec4 v_color;
+
+layout(set = 0, binding = 0) uniform Data {
+    mat4 view_proj;
+} uniforms;
+
+void main() {
+    v_color = color;
+    gl_Position = uniforms.view_proj * vec4(position, 1.0);
+}
\ No newline at end of file
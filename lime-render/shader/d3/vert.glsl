@@ -2,8 +2,11 @@
 
 layout(location = 0) in vec3 position;
 layout(location = 1) in vec3 normal;
+layout(location = 2) in mat4 transform;
+layout(location = 6) in vec4 color;
 
 layout(location = 0) out vec3 v_normal;
+layout(location = 1) out vec4 v_color;
 
 layout(set = 0, binding = 0) uniform Data {
     mat4 world;
@@ -12,7 +15,8 @@ layout(set = 0, binding = 0) uniform Data {
 } uniforms;
 
 void main() {
-    mat4 worldview = uniforms.view * uniforms.world;
+    mat4 worldview = uniforms.view * uniforms.world * transform;
     v_normal = transpose(inverse(mat3(worldview))) * normal;
+    v_color = color;
     gl_Position = uniforms.proj * worldview * vec4(position, 1.0);
 }
\ No newline at end of file
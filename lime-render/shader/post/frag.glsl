@@ -0,0 +1,43 @@
+#version 450
+
+layout(input_attachment_index = 0, set = 0, binding = 0) uniform subpassInput scene;
+
+layout(set = 0, binding = 1) uniform Data {
+    vec2 dimensions;
+    float exposure;
+    float gamma;
+    float vignette_strength;
+    float vignette_radius;
+    int tonemap;
+} uniforms;
+
+layout(location = 0) out vec4 f_color;
+
+const int TONEMAP_REINHARD = 1;
+const int TONEMAP_ACES = 2;
+
+vec3 aces(vec3 x) {
+    return clamp((x * (2.51 * x + 0.03)) / (x * (2.43 * x + 0.59) + 0.14), 0.0, 1.0);
+}
+
+void main() {
+    vec4 scene_color = subpassLoad(scene);
+    vec3 color = max(scene_color.rgb * uniforms.exposure, vec3(0.0));
+
+    if (uniforms.tonemap == TONEMAP_REINHARD) {
+        color = color / (color + vec3(1.0));
+    } else if (uniforms.tonemap == TONEMAP_ACES) {
+        color = aces(color);
+    }
+
+    color = pow(color, vec3(1.0 / uniforms.gamma));
+
+    vec2 uv = gl_FragCoord.xy / uniforms.dimensions;
+    float dist = distance(uv, vec2(0.5));
+    // smoothstep is undefined unless the outer edge is beyond the inner edge.
+    float outer = max(0.75, uniforms.vignette_radius + 0.05);
+    float vignette = smoothstep(uniforms.vignette_radius, outer, dist);
+    color *= 1.0 - uniforms.vignette_strength * vignette;
+
+    f_color = vec4(color, scene_color.a);
+}
\ No newline at end of file
@@ -0,0 +1,147 @@
+//! Capturing frame sequences from an `ImageTarget`.
+//!
+//! `capture_frames` runs the dispatcher once per frame, advancing the
+//! simulation by a fixed timestep each time, and writes every rendered frame
+//! out as it becomes available. Frames are copied into a ring of readback
+//! buffers, so the GPU can render one frame while earlier ones are written.
+//!
+//! If the render system skips a frame, for example after losing the device,
+//! the capture stops with an error rather than leaving a gap in the sequence.
+
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+use std::time::Duration;
+
+use failure::{err_msg, Fallible};
+use shrev::EventChannel;
+use specs::prelude::*;
+
+use {Context, ImageTarget, RenderError, Screenshot};
+
+/// How captured frames are written.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum CaptureFormat {
+    /// Numbered PNG files, `frame_00000.png` onwards, in a directory.
+    Png,
+    /// A single file holding the raw RGBA8 pixels of each frame in turn, for
+    /// example to pipe to `ffmpeg -f rawvideo -pix_fmt rgba`.
+    Raw,
+}
+
+#[derive(Clone, Debug)]
+pub struct CaptureOptions {
+    /// The directory for `Png` output, or the file for `Raw` output.
+    pub path: PathBuf,
+    pub format: CaptureFormat,
+    /// The number of frames to render.
+    pub frames: u64,
+    /// The simulated time between frames.
+    pub timestep: Duration,
+    /// The number of readback buffers. More buffers let the GPU run further
+    /// ahead of the frames being written.
+    pub ring_size: usize,
+}
+
+impl CaptureOptions {
+    pub fn new(path: impl Into<PathBuf>, format: CaptureFormat, frames: u64) -> Self {
+        CaptureOptions {
+            path: path.into(),
+            format,
+            frames,
+            timestep: Duration::from_secs(1) / 60,
+            ring_size: 3,
+        }
+    }
+}
+
+/// Render and write out a sequence of frames.
+///
+/// Before each frame `step` is called with the timestep, and should advance
+/// any simulated time by that amount. The world must have been initialized
+/// with an `ImageTarget`.
+///
+/// This fails if a frame is not rendered, since the sequence would otherwise
+/// have a gap in it.
+pub fn capture_frames<F>(
+    world: &mut World,
+    dispatcher: &mut Dispatcher,
+    options: &CaptureOptions,
+    mut step: F,
+) -> Fallible<()>
+where
+    F: FnMut(&mut World, Duration),
+{
+    let mut writer = Writer::new(options)?;
+    let mut errors = world
+        .write_resource::<EventChannel<RenderError>>()
+        .register_reader();
+    {
+        let ctx = world.read_resource::<Context>();
+        world
+            .write_resource::<ImageTarget>()
+            .start_capture(&ctx, options.ring_size)?;
+    }
+
+    let result = (|| {
+        let keep = options.ring_size.max(1) - 1;
+        for frame in 0..options.frames {
+            step(world, options.timestep);
+            dispatcher.dispatch(&world.res);
+            world.maintain();
+
+            let error = world
+                .read_resource::<EventChannel<RenderError>>()
+                .read(&mut errors)
+                .last()
+                .cloned();
+            let mut target = world.write_resource::<ImageTarget>();
+            if target.frames_captured() != Some(frame + 1) {
+                return Err(match error {
+                    Some(err) => err_msg(format!("frame {} was not rendered: {}", frame, err)),
+                    None => err_msg(format!("frame {} was not rendered", frame)),
+                });
+            }
+            target.take_captured(keep, |index, screenshot| writer.write(index, &screenshot))?;
+        }
+        world
+            .write_resource::<ImageTarget>()
+            .take_captured(0, |index, frame| writer.write(index, &frame))?;
+        writer.finish()
+    })();
+
+    world.write_resource::<ImageTarget>().stop_capture();
+    result
+}
+
+enum Writer {
+    Png(PathBuf),
+    Raw(BufWriter<File>),
+}
+
+impl Writer {
+    fn new(options: &CaptureOptions) -> Fallible<Self> {
+        Ok(match options.format {
+            CaptureFormat::Png => {
+                fs::create_dir_all(&options.path)?;
+                Writer::Png(options.path.clone())
+            }
+            CaptureFormat::Raw => Writer::Raw(BufWriter::new(File::create(&options.path)?)),
+        })
+    }
+
+    fn write(&mut self, index: u64, frame: &Screenshot) -> Fallible<()> {
+        trace!("Writing captured frame {}.", index);
+        match self {
+            Writer::Png(dir) => frame.save_png(dir.join(format!("frame_{:05}.png", index))),
+            Writer::Raw(file) => Ok(file.write_all(frame.data())?),
+        }
+    }
+
+    fn finish(&mut self) -> Fallible<()> {
+        match self {
+            Writer::Png(_) => Ok(()),
+            Writer::Raw(file) => Ok(file.flush()?),
+        }
+    }
+}
@@ -0,0 +1,369 @@
+mod names;
+
+use std::borrow::Cow;
+use std::error::Error;
+use std::fmt;
+use std::num::{ParseFloatError, ParseIntError};
+use std::str::FromStr;
+
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use vulkano::pipeline::vertex::{VertexMember, VertexMemberTy};
+
+/// A colour with linear RGB components and straight alpha.
+///
+/// Render targets use sRGB formats, so the components are gamma encoded when
+/// written to the screen. Colours picked in an image editor or written as CSS
+/// strings are sRGB encoded, and should be created with `Color::srgb` or
+/// parsed from a string rather than passed to `Color::rgb` directly.
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub struct Color {
+    pub r: f32,
+    pub g: f32,
+    pub b: f32,
+    pub a: f32,
+}
+
+impl Color {
+    pub fn rgb(r: f32, g: f32, b: f32) -> Self {
+        Color { r, g, b, a: 1.0 }
+    }
+
+    pub fn rgba(r: f32, g: f32, b: f32, a: f32) -> Self {
+        Color { r, g, b, a }
+    }
+
+    /// Create a colour from sRGB encoded components.
+    pub fn srgb(r: f32, g: f32, b: f32) -> Self {
+        Color::srgba(r, g, b, 1.0)
+    }
+
+    /// Create a colour from sRGB encoded components. Alpha is always linear.
+    pub fn srgba(r: f32, g: f32, b: f32, a: f32) -> Self {
+        Color {
+            r: srgb_to_linear(r),
+            g: srgb_to_linear(g),
+            b: srgb_to_linear(b),
+            a,
+        }
+    }
+
+    /// Create a colour from 8-bit sRGB encoded components.
+    pub fn srgb8([r, g, b, a]: [u8; 4]) -> Self {
+        let unorm = |c: u8| c as f32 / 255.0;
+        Color::srgba(unorm(r), unorm(g), unorm(b), unorm(a))
+    }
+
+    /// Create a colour from a hue in degrees, and saturation and lightness
+    /// from 0 to 1. The result is the same as the CSS `hsl()` function.
+    pub fn hsl(h: f32, s: f32, l: f32) -> Self {
+        Color::hsla(h, s, l, 1.0)
+    }
+
+    pub fn hsla(h: f32, s: f32, l: f32, a: f32) -> Self {
+        let chroma = (1.0 - (2.0 * l - 1.0).abs()) * s;
+        from_hue(h, chroma, l - chroma / 2.0, a)
+    }
+
+    /// Create a colour from a hue in degrees, and saturation and value from
+    /// 0 to 1. Like `hsl`, the resulting colour is sRGB encoded.
+    pub fn hsv(h: f32, s: f32, v: f32) -> Self {
+        Color::hsva(h, s, v, 1.0)
+    }
+
+    pub fn hsva(h: f32, s: f32, v: f32, a: f32) -> Self {
+        let chroma = v * s;
+        from_hue(h, chroma, v - chroma, a)
+    }
+
+    /// Look up a CSS named colour, ignoring case.
+    pub fn named(name: &str) -> Option<Self> {
+        if name.eq_ignore_ascii_case("transparent") {
+            return Some(Color::TRANSPARENT);
+        }
+
+        let name = name.to_ascii_lowercase();
+        names::NAMES
+            .binary_search_by_key(&name.as_str(), |&(name, _)| name)
+            .ok()
+            .map(|idx| {
+                let rgb = names::NAMES[idx].1;
+                Color::srgb8([(rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8, 255])
+            })
+    }
+
+    /// The sRGB encoded components of this colour.
+    pub fn to_srgb(self) -> [f32; 4] {
+        [
+            linear_to_srgb(self.r),
+            linear_to_srgb(self.g),
+            linear_to_srgb(self.b),
+            self.a,
+        ]
+    }
+
+    /// The sRGB encoded components of this colour, clamped and rounded to
+    /// 8 bits.
+    pub fn to_srgb8(self) -> [u8; 4] {
+        let [r, g, b, a] = self.to_srgb();
+        let unorm = |c: f32| (c.max(0.0).min(1.0) * 255.0).round() as u8;
+        [unorm(r), unorm(g), unorm(b), unorm(a)]
+    }
+
+    /// This colour with its components multiplied by its alpha.
+    pub fn premultiplied(self) -> Self {
+        Color {
+            r: self.r * self.a,
+            g: self.g * self.a,
+            b: self.b * self.a,
+            a: self.a,
+        }
+    }
+
+    pub const RED: Color = Color {
+        r: 1.0,
+        g: 0.0,
+        b: 0.0,
+        a: 1.0,
+    };
+    pub const GREEN: Color = Color {
+        r: 0.0,
+        g: 1.0,
+        b: 0.0,
+        a: 1.0,
+    };
+    pub const BLUE: Color = Color {
+        r: 0.0,
+        g: 0.0,
+        b: 1.0,
+        a: 1.0,
+    };
+    pub const BLACK: Color = Color {
+        r: 0.0,
+        g: 0.0,
+        b: 0.0,
+        a: 1.0,
+    };
+    pub const WHITE: Color = Color {
+        r: 1.0,
+        g: 1.0,
+        b: 1.0,
+        a: 1.0,
+    };
+    pub const TRANSPARENT: Color = Color {
+        r: 0.0,
+        g: 0.0,
+        b: 0.0,
+        a: 0.0,
+    };
+}
+
+fn srgb_to_linear(c: f32) -> f32 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn linear_to_srgb(c: f32) -> f32 {
+    if c <= 0.0031308 {
+        c * 12.92
+    } else {
+        1.055 * c.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+fn from_hue(h: f32, chroma: f32, min: f32, a: f32) -> Color {
+    let h = (h % 360.0 + 360.0) % 360.0 / 60.0;
+    let x = chroma * (1.0 - (h % 2.0 - 1.0).abs());
+    let (r, g, b) = match h as u32 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    Color::srgba(r + min, g + min, b + min, a)
+}
+
+impl PartialEq for Color {
+    fn eq(&self, other: &Self) -> bool {
+        ulps_eq!(self.r, other.r)
+            && ulps_eq!(self.g, other.g)
+            && ulps_eq!(self.b, other.b)
+            && ulps_eq!(self.a, other.a)
+    }
+}
+
+unsafe impl VertexMember for Color {
+    fn format() -> (VertexMemberTy, usize) {
+        (VertexMemberTy::F32, 4)
+    }
+}
+
+impl From<[f32; 4]> for Color {
+    fn from([r, g, b, a]: [f32; 4]) -> Self {
+        Color { r, g, b, a }
+    }
+}
+
+impl From<Color> for [f32; 4] {
+    fn from(color: Color) -> Self {
+        [color.r, color.g, color.b, color.a]
+    }
+}
+
+impl fmt::Display for Color {
+    /// Formats the colour as `#RRGGBB`, or `#RRGGBBAA` if it is translucent.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let [r, g, b, a] = self.to_srgb8();
+        write!(f, "#{:02X}{:02X}{:02X}", r, g, b)?;
+        if a != 255 {
+            write!(f, "{:02X}", a)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Color {
+    type Err = ParseError;
+
+    /// Parses a CSS colour: `#RGB`, `#RGBA`, `#RRGGBB`, `#RRGGBBAA`,
+    /// `rgb(r, g, b)`, `rgba(r, g, b, a)` or a named colour.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.starts_with('#') {
+            parse_hex(&s[1..])
+        } else if let Some(args) = function_args(s, "rgba") {
+            parse_rgb(args, true)
+        } else if let Some(args) = function_args(s, "rgb") {
+            parse_rgb(args, false)
+        } else if let Some(color) = Color::named(s) {
+            Ok(color)
+        } else if is_hex(s) {
+            Err(ParseError::MissingHash)
+        } else {
+            Err(ParseError::UnknownName)
+        }
+    }
+}
+
+fn parse_hex(s: &str) -> Result<Color, ParseError> {
+    if !s.is_ascii() {
+        return Err(ParseError::InvalidDigit);
+    }
+
+    let digit = |i: usize| u8::from_str_radix(&s[i..i + 1], 16).map(|n| n * 17);
+    let byte = |i: usize| u8::from_str_radix(&s[2 * i..2 * i + 2], 16);
+
+    let rgba = match s.len() {
+        3 => [digit(0)?, digit(1)?, digit(2)?, 255],
+        4 => [digit(0)?, digit(1)?, digit(2)?, digit(3)?],
+        6 => [byte(0)?, byte(1)?, byte(2)?, 255],
+        8 => [byte(0)?, byte(1)?, byte(2)?, byte(3)?],
+        _ => return Err(ParseError::InvalidLength),
+    };
+    Ok(Color::srgb8(rgba))
+}
+
+/// Whether `s` would be a hex colour if it started with `#`.
+fn is_hex(s: &str) -> bool {
+    [3, 4, 6, 8].contains(&s.len()) && s.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+fn function_args<'a>(s: &'a str, name: &str) -> Option<&'a str> {
+    if s.len() > name.len()
+        && s[..name.len()].eq_ignore_ascii_case(name)
+        && s[name.len()..].trim_start().starts_with('(')
+        && s.ends_with(')')
+    {
+        let args = s[name.len()..].trim_start();
+        Some(&args[1..args.len() - 1])
+    } else {
+        None
+    }
+}
+
+fn parse_rgb(args: &str, alpha: bool) -> Result<Color, ParseError> {
+    let args: Vec<&str> = args.split(',').map(str::trim).collect();
+    if args.len() != if alpha { 4 } else { 3 } {
+        return Err(ParseError::InvalidArguments);
+    }
+
+    let r = parse_component(args[0], 255.0)?;
+    let g = parse_component(args[1], 255.0)?;
+    let b = parse_component(args[2], 255.0)?;
+    let a = if alpha {
+        parse_component(args[3], 1.0)?
+    } else {
+        1.0
+    };
+    Ok(Color::srgba(r, g, b, a))
+}
+
+/// Parse a number or percentage, scaled so that `max` or `100%` is 1.
+fn parse_component(s: &str, max: f32) -> Result<f32, ParseError> {
+    let value = if s.ends_with('%') {
+        s[..s.len() - 1].trim_end().parse::<f32>()? / 100.0
+    } else {
+        s.parse::<f32>()? / max
+    };
+    Ok(value.max(0.0).min(1.0))
+}
+
+#[derive(Debug)]
+pub enum ParseError {
+    MissingHash,
+    InvalidLength,
+    InvalidDigit,
+    InvalidArguments,
+    UnknownName,
+}
+
+impl From<ParseIntError> for ParseError {
+    fn from(_: ParseIntError) -> Self {
+        ParseError::InvalidDigit
+    }
+}
+
+impl From<ParseFloatError> for ParseError {
+    fn from(_: ParseFloatError) -> Self {
+        ParseError::InvalidDigit
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            ParseError::MissingHash => "color string must start with the '#' symbol",
+            ParseError::InvalidLength => "invalid length for color string",
+            ParseError::InvalidDigit => "invalid digit in color string",
+            ParseError::InvalidArguments => "invalid number of arguments to color function",
+            ParseError::UnknownName => "unknown color name",
+        })
+    }
+}
+
+impl Error for ParseError {}
+
+impl<'de> Deserialize<'de> for Color {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Cow::<str>::deserialize(deserializer)?
+            .parse()
+            .map_err(de::Error::custom)
+    }
+}
+
+impl Serialize for Color {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_str(self)
+    }
+}
@@ -86,3 +86,15 @@ impl Context {
         once(self.transfer.family()).chain(once(self.graphics.family()))
     }
 }
+
+#[cfg(test)]
+impl Context {
+    /// A context on the first available device, for tests that need a GPU.
+    pub(crate) fn for_tests() -> Self {
+        use vulkano::instance::{Instance, InstanceExtensions};
+
+        let instance = Instance::new(None, &InstanceExtensions::none(), None).unwrap();
+        let phys = PhysicalDevice::enumerate(&instance).next().unwrap();
+        Context::new(phys, |_| true, &DeviceExtensions::none()).unwrap()
+    }
+}
@@ -0,0 +1,101 @@
+use vulkano::pipeline::blend::{AttachmentBlend, BlendFactor, BlendOp};
+
+use Color;
+
+/// How d2 content is combined with what is already drawn.
+///
+/// Colours are converted to premultiplied alpha when they are queued, so every
+/// mode blends premultiplied colours on the GPU.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum BlendMode {
+    /// Standard "over" blending of colours with straight alpha.
+    Alpha,
+    /// "Over" blending of colours whose components have already been multiplied
+    /// by their alpha.
+    Premultiplied,
+    /// Add the colour, weighted by its alpha, to the destination.
+    Additive,
+    /// Multiply the destination by the colour, weighted by its alpha.
+    Multiply,
+}
+
+impl BlendMode {
+    /// One mode for each pipeline, in the order given by `pipeline`.
+    pub(in d2) const PIPELINES: [BlendMode; 3] =
+        [BlendMode::Alpha, BlendMode::Additive, BlendMode::Multiply];
+
+    /// The index in `PIPELINES` of the mode whose pipeline draws this mode.
+    /// `Alpha` and `Premultiplied` share a pipeline, since both blend
+    /// premultiplied colours the same way.
+    pub(in d2) fn pipeline(self) -> usize {
+        match self {
+            BlendMode::Alpha | BlendMode::Premultiplied => 0,
+            BlendMode::Additive => 1,
+            BlendMode::Multiply => 2,
+        }
+    }
+
+    /// Convert a colour queued in this mode to premultiplied alpha.
+    pub(in d2) fn premultiply(self, color: Color) -> Color {
+        match self {
+            BlendMode::Premultiplied => color,
+            _ => color.premultiplied(),
+        }
+    }
+
+    /// Convert a colour queued in this mode to straight alpha, as used for
+    /// glyphs. Returns `None` for modes that glyphs cannot be drawn with.
+    pub(in d2) fn straight(self, color: Color) -> Option<Color> {
+        match self {
+            BlendMode::Alpha => Some(color),
+            BlendMode::Premultiplied if color.a > 0.0 => Some(Color {
+                r: color.r / color.a,
+                g: color.g / color.a,
+                b: color.b / color.a,
+                a: color.a,
+            }),
+            BlendMode::Premultiplied => Some(Color::rgba(0.0, 0.0, 0.0, 0.0)),
+            BlendMode::Additive | BlendMode::Multiply => None,
+        }
+    }
+
+    pub(in d2) fn attachment_blend(self) -> AttachmentBlend {
+        let (color_source, color_destination, alpha_source, alpha_destination) = match self {
+            BlendMode::Alpha | BlendMode::Premultiplied => (
+                BlendFactor::One,
+                BlendFactor::OneMinusSrcAlpha,
+                BlendFactor::One,
+                BlendFactor::OneMinusSrcAlpha,
+            ),
+            BlendMode::Additive => (
+                BlendFactor::One,
+                BlendFactor::One,
+                BlendFactor::Zero,
+                BlendFactor::One,
+            ),
+            BlendMode::Multiply => (
+                BlendFactor::DstColor,
+                BlendFactor::OneMinusSrcAlpha,
+                BlendFactor::Zero,
+                BlendFactor::One,
+            ),
+        };
+
+        AttachmentBlend {
+            enabled: true,
+            color_op: BlendOp::Add,
+            color_source,
+            color_destination,
+            alpha_op: BlendOp::Add,
+            alpha_source,
+            alpha_destination,
+            ..AttachmentBlend::pass_through()
+        }
+    }
+}
+
+impl Default for BlendMode {
+    fn default() -> Self {
+        BlendMode::Alpha
+    }
+}
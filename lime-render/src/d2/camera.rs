@@ -0,0 +1,65 @@
+use d2::{Point, Transform};
+
+/// A view onto 2D content positioned in world space rather than in logical
+/// window pixels.
+///
+/// Content drawn inside `d2::Renderer::with_camera` is transformed so that
+/// `position` appears at the centre of the window, scaled by `zoom` and
+/// rotated by `rotation`. Content drawn outside it, such as the UI, stays in
+/// screen space.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Camera2d {
+    /// The world point at the centre of the window.
+    pub position: Point,
+    /// The number of logical pixels per world unit. At zero, the whole world
+    /// is drawn at the centre of the window.
+    pub zoom: f32,
+    /// The rotation of the camera in radians. Positive angles turn the camera
+    /// clockwise, so content appears to rotate anticlockwise.
+    pub rotation: f32,
+}
+
+impl Camera2d {
+    pub fn new(position: Point) -> Self {
+        Camera2d {
+            position,
+            zoom: 1.0,
+            rotation: 0.0,
+        }
+    }
+
+    /// The transform from world space to logical pixels in a window of
+    /// `logical_size`.
+    pub fn view(&self, [width, height]: [f32; 2]) -> Transform {
+        Transform::translation(width / 2.0, height / 2.0)
+            * Transform::scale(self.zoom, self.zoom)
+            * Transform::rotation(-self.rotation)
+            * Transform::translation(-self.position.0, -self.position.1)
+    }
+
+    /// The transform from logical pixels in a window of `logical_size` to
+    /// world space. If `zoom` is zero, every point maps to `position`.
+    pub fn inverse_view(&self, [width, height]: [f32; 2]) -> Transform {
+        let scale = if self.zoom == 0.0 { 0.0 } else { 1.0 / self.zoom };
+        Transform::translation(self.position.0, self.position.1)
+            * Transform::rotation(self.rotation)
+            * Transform::scale(scale, scale)
+            * Transform::translation(-width / 2.0, -height / 2.0)
+    }
+
+    pub fn world_to_screen(&self, point: Point, logical_size: [f32; 2]) -> Point {
+        self.view(logical_size).transform_point(point)
+    }
+
+    /// Convert a point in logical pixels, such as the cursor position, to
+    /// world space.
+    pub fn screen_to_world(&self, point: Point, logical_size: [f32; 2]) -> Point {
+        self.inverse_view(logical_size).transform_point(point)
+    }
+}
+
+impl Default for Camera2d {
+    fn default() -> Self {
+        Camera2d::new(Point::origin())
+    }
+}
@@ -0,0 +1,154 @@
+use std::f32::consts::PI;
+
+use d2::Point;
+use Color;
+
+/// The number of segments around a radial gradient. This is a multiple of
+/// eight so that the spokes pass through the corners of the rectangle.
+const RADIAL_SEGMENTS: u32 = 32;
+
+/// The direction along which a linear gradient changes.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum GradientAxis {
+    /// From the left edge to the right edge.
+    Horizontal,
+    /// From the top edge to the bottom edge.
+    Vertical,
+}
+
+/// The vertices of a rectangle with a linear gradient, in the order expected
+/// by `QUAD_INDICES`.
+pub(in d2) fn linear(
+    min: Point,
+    max: Point,
+    start: Color,
+    end: Color,
+    axis: GradientAxis,
+) -> [(Point, Color); 4] {
+    let (bottom_left, top_left, bottom_right, top_right) = match axis {
+        GradientAxis::Horizontal => (start, start, end, end),
+        GradientAxis::Vertical => (end, start, end, start),
+    };
+    [
+        (Point(min.0, max.1), bottom_left),
+        (min, top_left),
+        (max, bottom_right),
+        (Point(max.0, min.1), top_right),
+    ]
+}
+
+/// The vertices and indices of a rectangle with a radial gradient.
+///
+/// The rectangle is drawn as a fan of triangles around its centre, out to the
+/// ellipse touching its edges, and a ring of quads from the ellipse out to the
+/// edges in the outer colour.
+pub(in d2) fn radial(
+    min: Point,
+    max: Point,
+    inner: Color,
+    outer: Color,
+) -> (Vec<(Point, Color)>, Vec<u32>) {
+    let center = Point((min.0 + max.0) / 2.0, (min.1 + max.1) / 2.0);
+    let half = Point((max.0 - min.0) / 2.0, (max.1 - min.1) / 2.0);
+    let point = |x: f32, y: f32| Point(center.0 + x * half.0, center.1 + y * half.1);
+
+    // The centre is vertex 0, followed by a point on the ellipse and a point on
+    // the edge of the rectangle for each spoke.
+    let mut vertices = Vec::with_capacity(1 + 2 * RADIAL_SEGMENTS as usize);
+    vertices.push((center, inner));
+    for i in 0..RADIAL_SEGMENTS {
+        let (sin, cos) = (2.0 * PI * i as f32 / RADIAL_SEGMENTS as f32).sin_cos();
+        let edge = cos.abs().max(sin.abs());
+        vertices.push((point(cos, sin), outer));
+        vertices.push((point(cos / edge, sin / edge), outer));
+    }
+
+    let mut indices = Vec::with_capacity(9 * RADIAL_SEGMENTS as usize);
+    for i in 0..RADIAL_SEGMENTS {
+        let ellipse = 1 + 2 * i;
+        let next_ellipse = 1 + 2 * ((i + 1) % RADIAL_SEGMENTS);
+        let (edge, next_edge) = (ellipse + 1, next_ellipse + 1);
+        indices.extend_from_slice(&[
+            0,
+            ellipse,
+            next_ellipse,
+            ellipse,
+            edge,
+            next_ellipse,
+            next_ellipse,
+            edge,
+            next_edge,
+        ]);
+    }
+    (vertices, indices)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use d2::tri::QUAD_INDICES;
+
+    const MIN: Point = Point(10.0, 20.0);
+    const MAX: Point = Point(110.0, 70.0);
+
+    fn close(lhs: Point, rhs: Point) -> bool {
+        (lhs.0 - rhs.0).abs() < 1e-3 && (lhs.1 - rhs.1).abs() < 1e-3
+    }
+
+    #[test]
+    fn linear_corners() {
+        let (start, end) = (Color::RED, Color::BLUE);
+        let corners = [
+            (Point(MIN.0, MIN.1), true, true),
+            (Point(MAX.0, MIN.1), false, true),
+            (Point(MIN.0, MAX.1), true, false),
+            (Point(MAX.0, MAX.1), false, false),
+        ];
+        for &axis in &[GradientAxis::Horizontal, GradientAxis::Vertical] {
+            let vertices = linear(MIN, MAX, start, end, axis);
+            assert_eq!(vertices.len(), 4);
+            assert!(QUAD_INDICES.iter().all(|&idx| (idx as usize) < vertices.len()));
+
+            for &(corner, left, top) in &corners {
+                let &(_, color) = vertices
+                    .iter()
+                    .find(|&&(position, _)| position == corner)
+                    .unwrap();
+                let at_start = match axis {
+                    GradientAxis::Horizontal => left,
+                    GradientAxis::Vertical => top,
+                };
+                assert_eq!(color, if at_start { start } else { end }, "{:?}", axis);
+            }
+        }
+    }
+
+    #[test]
+    fn radial_counts() {
+        let (vertices, indices) = radial(MIN, MAX, Color::WHITE, Color::BLACK);
+        let segments = RADIAL_SEGMENTS as usize;
+        assert_eq!(vertices.len(), 1 + 2 * segments);
+        assert_eq!(indices.len(), 9 * segments);
+        assert!(indices.iter().all(|&idx| (idx as usize) < vertices.len()));
+
+        assert_eq!(vertices[0], (Point(60.0, 45.0), Color::WHITE));
+        assert!(vertices[1..].iter().all(|&(_, color)| color == Color::BLACK));
+    }
+
+    #[test]
+    fn radial_spokes() {
+        let (vertices, _) = radial(MIN, MAX, Color::WHITE, Color::BLACK);
+        let edges: Vec<Point> = vertices[2..].iter().step_by(2).map(|&(p, _)| p).collect();
+
+        // Every spoke ends on the edge of the rectangle, and some end exactly in
+        // its corners.
+        for &edge in &edges {
+            let on_x = (edge.0 - MIN.0).abs() < 1e-3 || (edge.0 - MAX.0).abs() < 1e-3;
+            let on_y = (edge.1 - MIN.1).abs() < 1e-3 || (edge.1 - MAX.1).abs() < 1e-3;
+            assert!(on_x || on_y, "{:?}", edge);
+        }
+        for &corner in &[MIN, MAX, Point(MIN.0, MAX.1), Point(MAX.0, MIN.1)] {
+            assert!(edges.iter().any(|&edge| close(edge, corner)), "{:?}", corner);
+        }
+    }
+}
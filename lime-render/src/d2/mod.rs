@@ -1,70 +1,129 @@
+mod blend;
+mod camera;
 mod geom;
+mod gradient;
+mod ring;
+#[cfg(test)]
+mod tests;
+mod transform;
 mod tri;
 
+pub use self::blend::BlendMode;
+pub use self::camera::Camera2d;
 pub use self::geom::Point;
+pub use self::gradient::GradientAxis;
+pub use self::transform::Transform;
+#[cfg(all(feature = "hot-reload", debug_assertions))]
+pub(crate) use self::tri::{FRAG_PATH, VERT_PATH};
 
 use std::sync::Arc;
 
 use failure::Fallible;
 use rusttype::PositionedGlyph;
-use utils::throw;
-use vulkano::command_buffer::{AutoCommandBufferBuilder, DynamicState};
+use vulkano::command_buffer::AutoCommandBufferBuilder;
 use vulkano::device::Device;
 use vulkano::framebuffer::{RenderPassAbstract, Subpass};
 use vulkano_glyph::{FontId, GlyphBrush, Section as GlyphSection};
 
-use d2::tri::{TriangleBrush, TriangleSection};
-use Color;
+use d2::tri::{TriangleBrush, TriangleSection, QUAD_INDICES};
+use {Color, Frame, RenderStats};
 
+/// Draws 2D shapes and text over the scene.
+///
+/// Everything drawn belongs to a layer, which is 0 unless changed with
+/// `with_layer`. Layers are drawn in increasing order, so content in a higher
+/// layer appears above content in a lower one regardless of which system drew
+/// it first. Within a layer, content is drawn in the order it was queued.
+///
+/// Content is blended with `BlendMode::Alpha` unless changed with
+/// `with_blend_mode`, and is positioned in logical pixels transformed by the
+/// transforms pushed with `push_transform`.
 pub struct Renderer {
     tri_brush: TriangleBrush,
     glyph_brush: GlyphBrush<'static>,
-    sections: Vec<Section>,
+    sections: Vec<(i32, Section)>,
+    layer: i32,
+    blend_mode: BlendMode,
+    transform: Transform,
+    transform_stack: Vec<Transform>,
+    logical_size: [f32; 2],
+    glyphs: usize,
+    warned_glyph_blend: bool,
 }
 
 enum Section {
     Triangle(TriangleSection),
-    Glyph(GlyphSection),
+    Glyph(GlyphSection, Transform),
 }
 
 impl Renderer {
     pub(crate) fn new(
         device: &Arc<Device>,
         subpass: Subpass<Arc<RenderPassAbstract + Send + Sync>>,
-    ) -> Self {
-        let tri_brush = TriangleBrush::new(device, subpass.clone());
-        let glyph_brush = GlyphBrush::new(device, subpass).unwrap_or_else(throw);
-        Renderer {
+        frames: usize,
+        logical_size: [f32; 2],
+    ) -> Fallible<Self> {
+        let tri_brush = TriangleBrush::new(device, subpass.clone(), frames)?;
+        let glyph_brush = GlyphBrush::new(device, subpass)?;
+        Ok(Renderer {
             tri_brush,
             glyph_brush,
             sections: Vec::new(),
-        }
+            layer: 0,
+            blend_mode: BlendMode::default(),
+            transform: Transform::identity(),
+            transform_stack: Vec::new(),
+            logical_size,
+            glyphs: 0,
+            warned_glyph_blend: false,
+        })
+    }
+
+    /// Recompile the triangle shaders from source and rebuild their pipeline.
+    /// Glyphs are drawn by `vulkano_glyph`, whose shaders cannot be reloaded.
+    #[cfg(all(feature = "hot-reload", debug_assertions))]
+    pub(crate) fn reload_shaders(
+        &mut self,
+        device: &Arc<Device>,
+        subpass: Subpass<Arc<RenderPassAbstract + Send + Sync>>,
+    ) -> Fallible<()> {
+        self.tri_brush.reload_shaders(device, subpass)
     }
 
     pub(crate) fn commit(
         &mut self,
         mut cmd: AutoCommandBufferBuilder,
-        state: &DynamicState,
-        logical_size: [f32; 2],
+        frame: &Frame,
+        stats: &mut RenderStats,
     ) -> Fallible<AutoCommandBufferBuilder> {
-        for section in self.sections.drain(..) {
+        if !self.transform_stack.is_empty() {
+            warn!("Transforms were pushed without being popped.");
+            self.transform = Transform::identity();
+            self.transform_stack.clear();
+        }
+
+        self.logical_size = frame.logical_size;
+        let tri_frame = self.tri_brush.prepare(frame, stats)?;
+        sort_sections(&mut self.sections);
+        stats.sections += self.sections.len();
+        stats.glyphs += self.glyphs;
+        self.glyphs = 0;
+        for (_, section) in self.sections.drain(..) {
             match section {
-                Section::Triangle(section) => {
-                    cmd = self.tri_brush.draw(cmd, &section, state, logical_size)?;
-                }
-                Section::Glyph(section) => {
+                Section::Triangle(section) => if let Some(ref tri_frame) = tri_frame {
+                    cmd = self.tri_brush.draw(cmd, tri_frame, &section, frame.state, stats)?;
+                },
+                Section::Glyph(section, transform) => {
+                    // Glyph vertices are transformed after being scaled to
+                    // clip space.
                     cmd = self.glyph_brush.draw(
                         cmd,
                         &section,
-                        state,
-                        [
-                            [1.0, 0.0, 0.0, 0.0],
-                            [0.0, 1.0, 0.0, 0.0],
-                            [0.0, 0.0, 1.0, 0.0],
-                            [0.0, 0.0, 0.0, 1.0],
-                        ],
-                        logical_size,
+                        frame.state,
+                        transform.to_clip_matrix(frame.logical_size),
+                        frame.logical_size,
                     )?;
+                    stats.draw_calls += 1;
                 }
             }
         }
@@ -74,20 +133,216 @@ impl Renderer {
         Ok(cmd)
     }
 
+    /// Drop everything queued for a frame that could not be drawn, so that it
+    /// is neither drawn nor counted with the next one.
+    pub(crate) fn discard(&mut self) {
+        self.tri_brush.discard();
+        self.glyph_brush.clear();
+        self.sections.clear();
+        self.glyphs = 0;
+    }
+
+    /// The layer that content is currently drawn in.
+    pub fn layer(&self) -> i32 {
+        self.layer
+    }
+
+    /// Draw everything queued by `f` in layer `z`. Calls may be nested, and the
+    /// previous layer is restored afterwards.
+    pub fn with_layer<F, R>(&mut self, z: i32, f: F) -> R
+    where
+        F: FnOnce(&mut Self) -> R,
+    {
+        let prev = self.layer;
+        self.layer = z;
+        let result = f(self);
+        self.layer = prev;
+        result
+    }
+
+    /// The blend mode that content is currently drawn with.
+    pub fn blend_mode(&self) -> BlendMode {
+        self.blend_mode
+    }
+
+    /// Draw everything queued by `f` with blend mode `mode`. Calls may be
+    /// nested, and the previous mode is restored afterwards.
+    pub fn with_blend_mode<F, R>(&mut self, mode: BlendMode, f: F) -> R
+    where
+        F: FnOnce(&mut Self) -> R,
+    {
+        let prev = self.blend_mode;
+        self.blend_mode = mode;
+        let result = f(self);
+        self.blend_mode = prev;
+        result
+    }
+
+    /// The transform that content is currently drawn with.
+    pub fn transform(&self) -> Transform {
+        self.transform
+    }
+
+    /// Apply `transform` to everything drawn until the matching call to
+    /// `pop_transform`. It is combined with any transforms already pushed,
+    /// and is applied before them.
+    pub fn push_transform(&mut self, transform: Transform) {
+        self.transform_stack.push(self.transform);
+        self.transform = self.transform * transform;
+    }
+
+    /// Restore the transform from before the last call to `push_transform`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there is no matching call to `push_transform`.
+    pub fn pop_transform(&mut self) {
+        self.transform = self
+            .transform_stack
+            .pop()
+            .expect("pop_transform called without push_transform");
+    }
+
+    /// The size of the window in logical pixels, as of the last frame drawn or
+    /// the last time the window was resized.
+    pub fn logical_size(&self) -> [f32; 2] {
+        self.logical_size
+    }
+
+    pub(crate) fn set_logical_size(&mut self, logical_size: [f32; 2]) {
+        self.logical_size = logical_size;
+    }
+
+    /// Draw everything queued by `f` in the world space of `camera`, using the
+    /// window size from `logical_size`.
+    pub fn with_camera<F, R>(&mut self, camera: &Camera2d, f: F) -> R
+    where
+        F: FnOnce(&mut Self) -> R,
+    {
+        let view = camera.view(self.logical_size);
+        self.push_transform(view);
+        let result = f(self);
+        self.pop_transform();
+        result
+    }
+
     pub fn draw_tris(&mut self, vertices: &[Point], color: Color) {
-        let subsection = self.tri_brush.queue_tris(vertices, color);
-        if let Some(Section::Triangle(section)) = self.sections.last_mut() {
-            section.append(&subsection);
-            return;
-        }
-        self.sections.push(Section::Triangle(subsection));
+        let section = self
+            .tri_brush
+            .queue_tris(vertices, color, self.blend_mode, &self.transform);
+        self.push_triangles(section);
+    }
+
+    /// Draw a rectangle between two corners. This is cheaper than drawing the
+    /// same rectangle with `draw_tris`.
+    pub fn draw_rect(&mut self, min: Point, max: Point, color: Color) {
+        let section = self
+            .tri_brush
+            .queue_rect(min, max, color, self.blend_mode, &self.transform);
+        self.push_triangles(section);
+    }
+
+    /// Draw triangles with a colour for each vertex, which is interpolated
+    /// across each triangle.
+    pub fn draw_colored_tris(&mut self, vertices: &[(Point, Color)]) {
+        debug_assert!(vertices.len() % 3 == 0);
+        let section = self.tri_brush.queue_colored(
+            vertices,
+            0..vertices.len() as u32,
+            self.blend_mode,
+            &self.transform,
+        );
+        self.push_triangles(section);
+    }
+
+    /// Draw a rectangle whose colour changes linearly from `start` at one edge
+    /// to `end` at the opposite edge.
+    pub fn draw_linear_gradient(
+        &mut self,
+        min: Point,
+        max: Point,
+        start: Color,
+        end: Color,
+        axis: GradientAxis,
+    ) {
+        let vertices = gradient::linear(min, max, start, end, axis);
+        let section = self.tri_brush.queue_colored(
+            &vertices,
+            QUAD_INDICES.iter().cloned(),
+            self.blend_mode,
+            &self.transform,
+        );
+        self.push_triangles(section);
     }
 
+    /// Draw a rectangle whose colour changes from `inner` at its centre to
+    /// `outer` at the ellipse touching its edges, and is `outer` beyond that.
+    pub fn draw_radial_gradient(&mut self, min: Point, max: Point, inner: Color, outer: Color) {
+        let (vertices, indices) = gradient::radial(min, max, inner, outer);
+        let section =
+            self.tri_brush
+                .queue_colored(&vertices, indices, self.blend_mode, &self.transform);
+        self.push_triangles(section);
+    }
+
+    /// Draw glyphs from a font.
+    ///
+    /// Glyphs are drawn by `vulkano_glyph`, whose pipeline only supports
+    /// "over" blending. In `BlendMode::Premultiplied` the colour is converted
+    /// back to straight alpha, and in `Additive` and `Multiply` glyphs are
+    /// drawn as in `Alpha` with a warning.
     pub fn draw_glyphs<I>(&mut self, glyphs: I, font: FontId, color: Color)
     where
         I: IntoIterator<Item = PositionedGlyph<'static>>,
     {
+        let color = match self.blend_mode.straight(color) {
+            Some(color) => color,
+            None => {
+                if !self.warned_glyph_blend {
+                    warn!("Glyphs cannot be drawn with {:?}.", self.blend_mode);
+                    self.warned_glyph_blend = true;
+                }
+                color
+            }
+        };
+        let count = &mut self.glyphs;
+        let glyphs = glyphs.into_iter().inspect(|_| *count += 1);
         let section = self.glyph_brush.queue_glyphs(glyphs, font, color.into());
-        self.sections.push(Section::Glyph(section));
+        self.sections
+            .push((self.layer, Section::Glyph(section, self.transform)));
+    }
+
+    /// Add triangles to the frame, merging them with the previous section if
+    /// it also holds triangles in the same layer and pipeline so that they are
+    /// drawn in one call.
+    fn push_triangles(&mut self, subsection: TriangleSection) {
+        if let Some((layer, Section::Triangle(section))) = self.sections.last_mut() {
+            if *layer == self.layer && section.try_append(&subsection) {
+                return;
+            }
+        }
+        self.sections.push((self.layer, Section::Triangle(subsection)));
+    }
+}
+
+/// Sort sections by layer, keeping the order within each layer, and merge
+/// triangle sections that end up next to each other.
+fn sort_sections(sections: &mut Vec<(i32, Section)>) {
+    if sections.windows(2).all(|pair| pair[0].0 <= pair[1].0) {
+        return;
+    }
+
+    sections.sort_by_key(|&(layer, _)| layer);
+    let mut sorted: Vec<(i32, Section)> = Vec::with_capacity(sections.len());
+    for (layer, next) in sections.drain(..) {
+        if let (Some((_, Section::Triangle(section))), Section::Triangle(next)) =
+            (sorted.last_mut(), &next)
+        {
+            if section.try_append(next) {
+                continue;
+            }
+        }
+        sorted.push((layer, next));
     }
+    *sections = sorted;
 }
@@ -0,0 +1,62 @@
+use std::sync::Arc;
+
+use failure::Fallible;
+use vulkano::buffer::{BufferUsage, CpuAccessibleBuffer, TypedBufferAccess};
+use vulkano::device::Device;
+
+const MIN_CAPACITY: usize = 1024;
+
+/// A ring of persistently mapped buffers, one for each frame in flight.
+///
+/// Buffers grow to fit the largest frame written to them and are otherwise
+/// reused, so a steady stream of frames makes no allocations.
+pub(in d2) struct RingBuffer<T> {
+    device: Arc<Device>,
+    usage: BufferUsage,
+    slots: Vec<Option<Arc<CpuAccessibleBuffer<[T]>>>>,
+}
+
+impl<T> RingBuffer<T>
+where
+    T: Copy + Send + Sync + 'static,
+{
+    pub(in d2) fn new(device: &Arc<Device>, usage: BufferUsage, frames: usize) -> Self {
+        RingBuffer {
+            device: Arc::clone(device),
+            usage,
+            slots: vec![None; frames],
+        }
+    }
+
+    /// Write `data` to the start of the buffer for a frame slot. Only the first
+    /// `data.len()` elements of the returned buffer are initialized.
+    pub(in d2) fn write(
+        &mut self,
+        slot: usize,
+        data: &[T],
+    ) -> Fallible<Arc<CpuAccessibleBuffer<[T]>>> {
+        let slot = &mut self.slots[slot];
+
+        if let Some(ref buf) = *slot {
+            if buf.len() >= data.len() {
+                // The frame that last used this slot has finished, but the
+                // buffer may still be locked until vulkano cleans it up, in
+                // which case a new one is allocated.
+                if let Ok(mut mapping) = buf.write() {
+                    mapping[..data.len()].copy_from_slice(data);
+                    return Ok(Arc::clone(buf));
+                }
+            }
+        }
+
+        let capacity = data.len().next_power_of_two().max(MIN_CAPACITY);
+        trace!("Allocating ring buffer of {} elements.", capacity);
+        // Elements past `data.len()` are never read.
+        let buf = unsafe {
+            CpuAccessibleBuffer::uninitialized_array(Arc::clone(&self.device), capacity, self.usage)
+        }?;
+        buf.write()?[..data.len()].copy_from_slice(data);
+        *slot = Some(Arc::clone(&buf));
+        Ok(buf)
+    }
+}
@@ -0,0 +1,73 @@
+use std::ops::Range;
+
+use super::*;
+
+fn tri(range: Range<usize>) -> TriangleSection {
+    TriangleSection::new(range, BlendMode::Alpha)
+}
+
+/// Sort triangle sections queued in the given layers.
+fn sort(queued: Vec<(i32, TriangleSection)>) -> Vec<(i32, TriangleSection)> {
+    let mut sections: Vec<(i32, Section)> = queued
+        .into_iter()
+        .map(|(layer, section)| (layer, Section::Triangle(section)))
+        .collect();
+    sort_sections(&mut sections);
+    sections
+        .into_iter()
+        .map(|(layer, section)| match section {
+            Section::Triangle(section) => (layer, section),
+            Section::Glyph(..) => unreachable!(),
+        }).collect()
+}
+
+#[test]
+fn sort_interleaved_layers() {
+    let queued = vec![(0, tri(0..6)), (1, tri(6..12)), (0, tri(12..18))];
+    assert_eq!(
+        sort(queued),
+        vec![(0, tri(0..6)), (0, tri(12..18)), (1, tri(6..12))]
+    );
+}
+
+#[test]
+fn sort_merges_adjacent_ranges() {
+    let queued = vec![(0, tri(0..6)), (1, tri(12..18)), (0, tri(6..12))];
+    assert_eq!(sort(queued), vec![(0, tri(0..12)), (1, tri(12..18))]);
+}
+
+#[test]
+fn sort_keeps_blend_modes_apart() {
+    let queued = vec![
+        (0, tri(0..6)),
+        (1, tri(12..18)),
+        (0, TriangleSection::new(6..12, BlendMode::Additive)),
+    ];
+    assert_eq!(
+        sort(queued),
+        vec![
+            (0, tri(0..6)),
+            (0, TriangleSection::new(6..12, BlendMode::Additive)),
+            (1, tri(12..18)),
+        ]
+    );
+}
+
+#[test]
+fn alpha_and_premultiplied_share_a_pipeline() {
+    let mut section = tri(0..6);
+    assert!(section.try_append(&TriangleSection::new(6..12, BlendMode::Premultiplied)));
+    assert_eq!(section, tri(0..12));
+    assert!(!section.try_append(&TriangleSection::new(12..18, BlendMode::Multiply)));
+}
+
+#[test]
+fn straight_alpha() {
+    let color = Color::rgba(0.5, 0.25, 0.0, 0.5);
+    assert_eq!(
+        BlendMode::Premultiplied.straight(color),
+        Some(Color::rgba(1.0, 0.5, 0.0, 0.5))
+    );
+    assert_eq!(BlendMode::Alpha.straight(color), Some(color));
+    assert!(BlendMode::Additive.straight(color).is_none());
+}
@@ -0,0 +1,109 @@
+use std::ops::Mul;
+
+use d2::Point;
+
+/// A 2D affine transform in logical pixels, stored as the columns of a 3x2
+/// matrix: the images of the x and y axes, then the translation.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Transform(pub [[f32; 2]; 3]);
+
+impl Transform {
+    pub fn identity() -> Self {
+        Transform::scale(1.0, 1.0)
+    }
+
+    pub fn translation(x: f32, y: f32) -> Self {
+        Transform([[1.0, 0.0], [0.0, 1.0], [x, y]])
+    }
+
+    pub fn scale(x: f32, y: f32) -> Self {
+        Transform([[x, 0.0], [0.0, y], [0.0, 0.0]])
+    }
+
+    /// A rotation of `angle` radians around the origin. Since y points down,
+    /// positive angles rotate clockwise on screen.
+    pub fn rotation(angle: f32) -> Self {
+        let (sin, cos) = angle.sin_cos();
+        Transform([[cos, sin], [-sin, cos], [0.0, 0.0]])
+    }
+
+    pub fn transform_point(&self, Point(x, y): Point) -> Point {
+        let m = &self.0;
+        Point(
+            m[0][0] * x + m[1][0] * y + m[2][0],
+            m[0][1] * x + m[1][1] * y + m[2][1],
+        )
+    }
+
+    pub fn is_identity(&self) -> bool {
+        *self == Transform::identity()
+    }
+
+    /// The equivalent transform in clip space, for a frame of `logical_size`.
+    pub(in d2) fn to_clip_matrix(&self, [width, height]: [f32; 2]) -> [[f32; 4]; 4] {
+        let to_clip =
+            Transform::translation(-1.0, -1.0) * Transform::scale(2.0 / width, 2.0 / height);
+        let from_clip =
+            Transform::scale(width / 2.0, height / 2.0) * Transform::translation(1.0, 1.0);
+        let [x, y, offset] = (to_clip * *self * from_clip).0;
+        [
+            [x[0], x[1], 0.0, 0.0],
+            [y[0], y[1], 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [offset[0], offset[1], 0.0, 1.0],
+        ]
+    }
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Transform::identity()
+    }
+}
+
+/// Combine two transforms. The result applies `rhs` first, then `self`.
+impl Mul for Transform {
+    type Output = Transform;
+
+    fn mul(self, rhs: Transform) -> Transform {
+        let m = &self.0;
+        let linear = |[x, y]: [f32; 2]| [m[0][0] * x + m[1][0] * y, m[0][1] * x + m[1][1] * y];
+        let Point(tx, ty) = self.transform_point(Point(rhs.0[2][0], rhs.0[2][1]));
+        Transform([linear(rhs.0[0]), linear(rhs.0[1]), [tx, ty]])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A point in logical pixels in clip space, as computed by the d2 vertex
+    /// shader and by `vulkano_glyph` for glyph vertices.
+    fn to_clip(Point(x, y): Point, [width, height]: [f32; 2]) -> [f32; 4] {
+        [2.0 * x / width - 1.0, 2.0 * y / height - 1.0, 0.0, 1.0]
+    }
+
+    #[test]
+    fn clip_matrix() {
+        // Glyphs are transformed by the clip matrix after being moved into clip
+        // space, while triangles are transformed before. Both must end up in
+        // the same place.
+        let size = [800.0, 600.0];
+        let transform = Transform::translation(400.0, 300.0)
+            * Transform::rotation(0.7)
+            * Transform::scale(2.0, 1.5)
+            * Transform::translation(-50.0, -20.0);
+        let matrix = transform.to_clip_matrix(size);
+
+        for &point in &[Point(0.0, 0.0), Point(50.0, 20.0), Point(123.0, -45.0)] {
+            let clip = to_clip(point, size);
+            let glyph: Vec<f32> = (0..4)
+                .map(|row| (0..4).map(|col| matrix[col][row] * clip[col]).sum())
+                .collect();
+            let triangle = to_clip(transform.transform_point(point), size);
+            for (glyph, triangle) in glyph.iter().zip(&triangle) {
+                assert!((glyph - triangle).abs() < 1e-5, "{:?}", point);
+            }
+        }
+    }
+}
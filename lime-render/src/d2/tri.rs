@@ -1,19 +1,38 @@
+use std::mem;
 use std::ops::Range;
 use std::sync::Arc;
 
-use failure::Fallible;
-use utils::throw;
-use vulkano::buffer::CpuBufferPool;
+use failure::{err_msg, Fallible};
+use vulkano::buffer::{BufferSlice, BufferUsage, CpuAccessibleBuffer, CpuBufferPool};
 use vulkano::command_buffer::{AutoCommandBufferBuilder, DynamicState};
-use vulkano::descriptor::descriptor_set::FixedSizeDescriptorSetsPool;
+use vulkano::descriptor::descriptor_set::{DescriptorSet, FixedSizeDescriptorSetsPool};
 use vulkano::descriptor::PipelineLayoutAbstract;
 use vulkano::device::Device;
 use vulkano::framebuffer::{RenderPassAbstract, Subpass};
+use vulkano::pipeline::shader::ShaderModule;
 use vulkano::pipeline::vertex::SingleBufferDefinition;
 use vulkano::pipeline::GraphicsPipeline;
 
-use d2::Point;
-use Color;
+use d2::ring::RingBuffer;
+use d2::{BlendMode, Point, Transform};
+#[cfg(all(feature = "hot-reload", debug_assertions))]
+use reload::{self, ShaderType};
+use {Color, Frame, RenderStats};
+
+/// The shader sources, relative to the `shader` directory.
+#[cfg(all(feature = "hot-reload", debug_assertions))]
+pub(crate) const VERT_PATH: &str = "d2/vert.glsl";
+#[cfg(all(feature = "hot-reload", debug_assertions))]
+pub(crate) const FRAG_PATH: &str = "d2/frag.glsl";
+/// The sources compiled into the crate.
+#[cfg(all(feature = "hot-reload", debug_assertions))]
+const VERT_SOURCE: &str = include_str!("../../shader/d2/vert.glsl");
+#[cfg(all(feature = "hot-reload", debug_assertions))]
+const FRAG_SOURCE: &str = include_str!("../../shader/d2/frag.glsl");
+
+/// The two triangles of a quad with vertices in the order bottom left, top
+/// left, bottom right, top right.
+pub(in d2) const QUAD_INDICES: [u32; 6] = [0, 1, 2, 2, 1, 3];
 
 type Pipeline = Arc<
     GraphicsPipeline<
@@ -24,89 +43,264 @@ type Pipeline = Arc<
 >;
 
 pub(in d2) struct TriangleBrush {
-    vbuf: CpuBufferPool<Vertex>,
+    vertices: RingBuffer<Vertex>,
+    indices: RingBuffer<u32>,
     ubuf: CpuBufferPool<vs::ty::Data>,
-    pipe: Pipeline,
+    pipes: Vec<Pipeline>,
     pool: FixedSizeDescriptorSetsPool<Pipeline>,
-    queued: Vec<Vertex>,
+    queued_vertices: Vec<Vertex>,
+    queued_indices: Vec<u32>,
 }
 
-#[derive(Clone, Debug)]
+/// A range of queued indices drawn with one blend mode.
+#[derive(Clone, Debug, PartialEq)]
 pub(in d2) struct TriangleSection {
     range: Range<usize>,
+    mode: BlendMode,
+}
+
+/// The buffers and descriptor set shared by every section drawn in a frame.
+pub(in d2) struct TriangleFrame {
+    vbuf: Arc<CpuAccessibleBuffer<[Vertex]>>,
+    ibuf: Arc<CpuAccessibleBuffer<[u32]>>,
+    set: Arc<DescriptorSet + Send + Sync>,
 }
 
 impl TriangleBrush {
     pub(in d2) fn new(
         device: &Arc<Device>,
         subpass: Subpass<Arc<RenderPassAbstract + Send + Sync>>,
-    ) -> Self {
-        let vs = vs::Shader::load(Arc::clone(device)).unwrap_or_else(throw);
-        let fs = fs::Shader::load(Arc::clone(device)).unwrap_or_else(throw);
-
-        let pipe = Arc::new(
-            GraphicsPipeline::start()
-                .vertex_input_single_buffer::<Vertex>()
-                .vertex_shader(vs.main_entry_point(), ())
-                .triangle_list()
-                .viewports_dynamic_scissors_irrelevant(1)
-                .fragment_shader(fs.main_entry_point(), ())
-                .render_pass(subpass)
-                .build(Arc::clone(device))
-                .unwrap_or_else(throw),
-        );
+        frames: usize,
+    ) -> Fallible<Self> {
+        let vs = vs::Shader::load(Arc::clone(device))?;
+        let fs = fs::Shader::load(Arc::clone(device))?;
+        let pipes = create_pipelines(device, subpass, vs.module(), fs.module())?;
 
-        let vbuf = CpuBufferPool::vertex_buffer(Arc::clone(device));
+        let vertices = RingBuffer::new(device, BufferUsage::vertex_buffer(), frames);
+        let indices = RingBuffer::new(device, BufferUsage::index_buffer(), frames);
         let ubuf = CpuBufferPool::uniform_buffer(Arc::clone(device));
 
-        let pool = FixedSizeDescriptorSetsPool::new(Arc::clone(&pipe), 0);
+        // The pipelines differ only in blend state, so share one layout.
+        let pool = FixedSizeDescriptorSetsPool::new(Arc::clone(&pipes[0]), 0);
 
-        TriangleBrush {
-            pipe,
-            vbuf,
+        Ok(TriangleBrush {
+            pipes,
+            vertices,
+            indices,
             ubuf,
             pool,
-            queued: Vec::new(),
+            queued_vertices: Vec::new(),
+            queued_indices: Vec::new(),
+        })
+    }
+
+    /// Recompile the shaders from source and rebuild the pipeline. On failure
+    /// the old pipeline is kept.
+    #[cfg(all(feature = "hot-reload", debug_assertions))]
+    pub(in d2) fn reload_shaders(
+        &mut self,
+        device: &Arc<Device>,
+        subpass: Subpass<Arc<RenderPassAbstract + Send + Sync>>,
+    ) -> Fallible<()> {
+        let vs = reload::compile(device, VERT_PATH, VERT_SOURCE, ShaderType::Vertex)?;
+        let fs = reload::compile(device, FRAG_PATH, FRAG_SOURCE, ShaderType::Fragment)?;
+        let pipes = create_pipelines(device, subpass, &vs, &fs)?;
+
+        self.pool = FixedSizeDescriptorSetsPool::new(Arc::clone(&pipes[0]), 0);
+        self.pipes = pipes;
+        Ok(())
+    }
+
+    /// Upload everything queued this frame in one go, returning `None` if
+    /// nothing was queued.
+    pub(in d2) fn prepare(
+        &mut self,
+        frame: &Frame,
+        stats: &mut RenderStats,
+    ) -> Fallible<Option<TriangleFrame>> {
+        if self.queued_indices.is_empty() {
+            self.queued_vertices.clear();
+            return Ok(None);
         }
+
+        stats.vertices += self.queued_vertices.len();
+        stats.indices += self.queued_indices.len();
+        stats.uploaded_bytes += self.queued_vertices.len() * mem::size_of::<Vertex>()
+            + self.queued_indices.len() * mem::size_of::<u32>()
+            + mem::size_of::<vs::ty::Data>();
+
+        let vbuf = self.vertices.write(frame.slot, &self.queued_vertices)?;
+        let ibuf = self.indices.write(frame.slot, &self.queued_indices)?;
+        self.queued_vertices.clear();
+        self.queued_indices.clear();
+
+        let ubuf = self.ubuf.next(vs::ty::Data {
+            dimensions: frame.logical_size,
+        })?;
+        let set = Arc::new(self.pool.next().add_buffer(ubuf)?.build()?);
+
+        Ok(Some(TriangleFrame { vbuf, ibuf, set }))
     }
 
     pub(in d2) fn draw(
-        &mut self,
+        &self,
         cmd: AutoCommandBufferBuilder,
+        frame: &TriangleFrame,
         section: &TriangleSection,
         state: &DynamicState,
-        logical_size: [f32; 2],
+        stats: &mut RenderStats,
     ) -> Fallible<AutoCommandBufferBuilder> {
         if section.range.len() == 0 {
             return Ok(cmd);
         }
+        stats.draw_calls += 1;
 
-        let vbuf = self
-            .vbuf
-            .chunk(self.queued[section.range.clone()].iter().cloned())?;
-        let ubuf = self.ubuf.next(vs::ty::Data {
-            dimensions: logical_size,
-        })?;
-        let set = self.pool.next().add_buffer(ubuf)?.build()?;
+        let ibuf = BufferSlice::from_typed_buffer_access(Arc::clone(&frame.ibuf))
+            .slice(section.range.clone())
+            .ok_or_else(|| err_msg("triangle section out of range"))?;
+
+        Ok(cmd.draw_indexed(
+            Arc::clone(&self.pipes[section.mode.pipeline()]),
+            state,
+            Arc::clone(&frame.vbuf),
+            ibuf,
+            Arc::clone(&frame.set),
+            (),
+        )?)
+    }
 
-        Ok(cmd.draw(Arc::clone(&self.pipe), state, vbuf, set, ())?)
+    /// Drop the queued vertices and indices.
+    pub(in d2) fn discard(&mut self) {
+        self.queued_vertices.clear();
+        self.queued_indices.clear();
     }
 
-    pub(in d2) fn queue_tris(&mut self, vertices: &[Point], color: Color) -> TriangleSection {
+    pub(in d2) fn queue_tris(
+        &mut self,
+        vertices: &[Point],
+        color: Color,
+        mode: BlendMode,
+        transform: &Transform,
+    ) -> TriangleSection {
         debug_assert!(vertices.len() % 3 == 0);
-        let start = self.queued.len();
-        self.queued
-            .extend(vertices.iter().map(|&v| Vertex::new(v, color)));
-        let end = self.queued.len();
-        TriangleSection { range: start..end }
+        let color = mode.premultiply(color);
+        let base = self.queued_vertices.len() as u32;
+        if transform.is_identity() {
+            self.queued_vertices
+                .extend(vertices.iter().map(|&v| Vertex::new(v, color)));
+        } else {
+            self.queued_vertices.extend(
+                vertices
+                    .iter()
+                    .map(|&v| Vertex::new(transform.transform_point(v), color)),
+            );
+        }
+        self.queue_indices((0..vertices.len() as u32).map(|idx| base + idx), mode)
+    }
+
+    /// Queue a rectangle as four vertices and six indices. The rectangle is
+    /// axis-aligned before it is transformed.
+    pub(in d2) fn queue_rect(
+        &mut self,
+        min: Point,
+        max: Point,
+        color: Color,
+        mode: BlendMode,
+        transform: &Transform,
+    ) -> TriangleSection {
+        let color = mode.premultiply(color);
+        let base = self.queued_vertices.len() as u32;
+        let corners = [Point(min.0, max.1), min, max, Point(max.0, min.1)];
+        self.queued_vertices.extend(
+            corners
+                .iter()
+                .map(|&corner| Vertex::new(transform.transform_point(corner), color)),
+        );
+        self.queue_indices(QUAD_INDICES.iter().map(|&idx| base + idx), mode)
+    }
+
+    /// Queue vertices with their own colours, drawn as the triangles listed by
+    /// `indices`, which are relative to the first vertex.
+    pub(in d2) fn queue_colored<I>(
+        &mut self,
+        vertices: &[(Point, Color)],
+        indices: I,
+        mode: BlendMode,
+        transform: &Transform,
+    ) -> TriangleSection
+    where
+        I: IntoIterator<Item = u32>,
+    {
+        let base = self.queued_vertices.len() as u32;
+        self.queued_vertices
+            .extend(vertices.iter().map(|&(position, color)| {
+                Vertex::new(transform.transform_point(position), mode.premultiply(color))
+            }));
+        self.queue_indices(indices.into_iter().map(|idx| base + idx), mode)
+    }
+
+    fn queue_indices<I>(&mut self, indices: I, mode: BlendMode) -> TriangleSection
+    where
+        I: IntoIterator<Item = u32>,
+    {
+        let start = self.queued_indices.len();
+        self.queued_indices.extend(indices);
+        let end = self.queued_indices.len();
+        TriangleSection {
+            range: start..end,
+            mode,
+        }
     }
 }
 
 impl TriangleSection {
-    pub(in d2) fn append(&mut self, next: &TriangleSection) {
-        debug_assert_eq!(self.range.end, next.range.start);
-        self.range.end = next.range.end;
+    #[cfg(test)]
+    pub(in d2) fn new(range: Range<usize>, mode: BlendMode) -> Self {
+        TriangleSection { range, mode }
+    }
+
+    /// Append `next` if it directly follows this section and is drawn with the
+    /// same pipeline.
+    pub(in d2) fn try_append(&mut self, next: &TriangleSection) -> bool {
+        if self.range.end == next.range.start && self.mode.pipeline() == next.mode.pipeline() {
+            self.range.end = next.range.end;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// Create a pipeline for each mode in `BlendMode::PIPELINES`.
+fn create_pipelines(
+    device: &Arc<Device>,
+    subpass: Subpass<Arc<RenderPassAbstract + Send + Sync>>,
+    vs_module: &ShaderModule,
+    fs_module: &ShaderModule,
+) -> Fallible<Vec<Pipeline>> {
+    let mut pipes = Vec::with_capacity(BlendMode::PIPELINES.len());
+    for mode in &BlendMode::PIPELINES {
+        // Both modules are compiled from the sources of `vs` and `fs`.
+        let (vs, fs) = unsafe {
+            (
+                main_entry_point!(vs_module, vs, vertex, Vertex),
+                main_entry_point!(fs_module, fs, fragment, Fragment),
+            )
+        };
+
+        pipes.push(Arc::new(
+            GraphicsPipeline::start()
+                .vertex_input_single_buffer::<Vertex>()
+                .vertex_shader(vs, ())
+                .triangle_list()
+                .viewports_dynamic_scissors_irrelevant(1)
+                .fragment_shader(fs, ())
+                .blend_collective(mode.attachment_blend())
+                .render_pass(subpass.clone())
+                .build(Arc::clone(device))?,
+        ));
     }
+    Ok(pipes)
 }
 
 #[derive(Copy, Clone, Debug)]
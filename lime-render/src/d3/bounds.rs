@@ -0,0 +1,114 @@
+use std::f32;
+
+use d3::{Matrix, Vector};
+
+/// An axis-aligned bounding box.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Aabb {
+    pub min: Vector,
+    pub max: Vector,
+}
+
+/// A bounding sphere.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Sphere {
+    pub center: Vector,
+    pub radius: f32,
+}
+
+impl Aabb {
+    /// An empty box, which contains no points.
+    pub fn empty() -> Self {
+        Aabb {
+            min: Vector(f32::INFINITY, f32::INFINITY, f32::INFINITY),
+            max: Vector(f32::NEG_INFINITY, f32::NEG_INFINITY, f32::NEG_INFINITY),
+        }
+    }
+
+    pub fn from_points(points: impl IntoIterator<Item = Vector>) -> Self {
+        let mut aabb = Aabb::empty();
+        for point in points {
+            aabb.extend(point);
+        }
+        aabb
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.min.0 > self.max.0 || self.min.1 > self.max.1 || self.min.2 > self.max.2
+    }
+
+    pub fn extend(&mut self, point: Vector) {
+        self.min = Vector(
+            self.min.0.min(point.0),
+            self.min.1.min(point.1),
+            self.min.2.min(point.2),
+        );
+        self.max = Vector(
+            self.max.0.max(point.0),
+            self.max.1.max(point.1),
+            self.max.2.max(point.2),
+        );
+    }
+
+    pub fn center(&self) -> Vector {
+        (self.min + self.max) * 0.5
+    }
+
+    pub fn contains(&self, point: Vector) -> bool {
+        self.min.0 <= point.0
+            && point.0 <= self.max.0
+            && self.min.1 <= point.1
+            && point.1 <= self.max.1
+            && self.min.2 <= point.2
+            && point.2 <= self.max.2
+    }
+
+    /// The eight corners of the box.
+    pub fn corners(&self) -> [Vector; 8] {
+        let mut corners = [self.min; 8];
+        for (i, corner) in corners.iter_mut().enumerate() {
+            *corner = Vector(
+                if i & 1 == 0 { self.min.0 } else { self.max.0 },
+                if i & 2 == 0 { self.min.1 } else { self.max.1 },
+                if i & 4 == 0 { self.min.2 } else { self.max.2 },
+            );
+        }
+        corners
+    }
+
+    /// Get a box containing this box after it has been transformed.
+    pub fn transform(&self, matrix: &Matrix) -> Aabb {
+        if self.is_empty() {
+            return *self;
+        }
+        Aabb::from_points(self.corners().iter().map(|&p| matrix.transform_point(p)))
+    }
+}
+
+impl Sphere {
+    /// A sphere centred on the middle of the points' bounding box which
+    /// contains all of the points.
+    pub fn from_points(points: &[Vector]) -> Self {
+        if points.is_empty() {
+            return Sphere {
+                center: Vector::origin(),
+                radius: 0.0,
+            };
+        }
+
+        let center = Aabb::from_points(points.iter().cloned()).center();
+        let radius = points
+            .iter()
+            .map(|&p| (p - center).length())
+            .fold(0.0, f32::max);
+        Sphere { center, radius }
+    }
+
+    /// Get a sphere containing this sphere after it has been transformed.
+    pub fn transform(&self, matrix: &Matrix) -> Sphere {
+        Sphere {
+            center: matrix.transform_point(self.center),
+            radius: self.radius * matrix.max_scale(),
+        }
+    }
+}
@@ -0,0 +1,360 @@
+use std::collections::HashMap;
+use std::f32::consts::PI;
+
+use vulkano::sync::GpuFuture;
+
+use d3::{Mesh, Vector, Vertex};
+use Context;
+
+/// CPU-side mesh data that can be edited before being uploaded as a `Mesh`.
+///
+/// Indices are stored as `u32` and narrowed to `u16` on upload when the mesh
+/// is small enough.
+#[derive(Clone, Debug, Default)]
+pub struct MeshBuilder {
+    positions: Vec<Vector>,
+    normals: Vec<Vector>,
+    uvs: Vec<[f32; 2]>,
+    indices: Vec<u32>,
+    keep_cpu_data: bool,
+}
+
+impl MeshBuilder {
+    pub fn new() -> Self {
+        MeshBuilder::default()
+    }
+
+    /// Create a builder from separate vertex attributes. Missing normals or
+    /// texture coordinates may be passed as empty vectors.
+    pub(in d3) fn from_parts(
+        positions: Vec<Vector>,
+        mut normals: Vec<Vector>,
+        mut uvs: Vec<[f32; 2]>,
+        indices: Vec<u32>,
+    ) -> Self {
+        normals.resize(positions.len(), Vector::origin());
+        uvs.resize(positions.len(), [0.0, 0.0]);
+        MeshBuilder {
+            positions,
+            normals,
+            uvs,
+            indices,
+            keep_cpu_data: false,
+        }
+    }
+
+    pub fn positions(&self) -> &[Vector] {
+        &self.positions
+    }
+
+    pub fn normals(&self) -> &[Vector] {
+        &self.normals
+    }
+
+    pub fn uvs(&self) -> &[[f32; 2]] {
+        &self.uvs
+    }
+
+    pub fn indices(&self) -> &[u32] {
+        &self.indices
+    }
+
+    /// Whether the mesh has too many vertices to be indexed with `u16`.
+    pub fn needs_u32_indices(&self) -> bool {
+        self.positions.len() > u16::max_value() as usize + 1
+    }
+
+    /// Add a vertex, returning its index.
+    pub fn vertex(&mut self, position: Vector, normal: Vector, uv: [f32; 2]) -> u32 {
+        let idx = self.positions.len() as u32;
+        self.positions.push(position);
+        self.normals.push(normal);
+        self.uvs.push(uv);
+        idx
+    }
+
+    pub fn triangle(&mut self, a: u32, b: u32, c: u32) -> &mut Self {
+        self.indices.extend_from_slice(&[a, b, c]);
+        self
+    }
+
+    /// Add a quad with corners in counter-clockwise order.
+    pub fn quad(&mut self, a: u32, b: u32, c: u32, d: u32) -> &mut Self {
+        self.triangle(a, b, c).triangle(a, c, d)
+    }
+
+    /// Append the vertices and triangles of another mesh.
+    pub fn append(&mut self, other: &MeshBuilder) -> &mut Self {
+        let offset = self.positions.len() as u32;
+        self.positions.extend_from_slice(&other.positions);
+        self.normals.extend_from_slice(&other.normals);
+        self.uvs.extend_from_slice(&other.uvs);
+        self.indices
+            .extend(other.indices.iter().map(|&idx| idx + offset));
+        self
+    }
+
+    /// Replace the normals with the area-weighted average of the normals of
+    /// the triangles sharing each vertex. Vertices are only shared if they have
+    /// the same index, so `weld` may need to be called first.
+    pub fn smooth_normals(&mut self) -> &mut Self {
+        let mut normals = vec![Vector::origin(); self.positions.len()];
+        for tri in self.indices.chunks(3) {
+            let normal = self.face_normal(tri);
+            for &idx in tri {
+                normals[idx as usize] += normal;
+            }
+        }
+        self.normals = normals.into_iter().map(Vector::normalize).collect();
+        self
+    }
+
+    /// Give every triangle its own vertices, with normals perpendicular to the
+    /// triangle.
+    pub fn flat_normals(&mut self) -> &mut Self {
+        let mut flat = MeshBuilder::new();
+        for tri in self.indices.chunks(3) {
+            let normal = self.face_normal(tri).normalize();
+            let mut corners = [0; 3];
+            for (corner, &idx) in corners.iter_mut().zip(tri) {
+                let idx = idx as usize;
+                *corner = flat.vertex(self.positions[idx], normal, self.uvs[idx]);
+            }
+            flat.triangle(corners[0], corners[1], corners[2]);
+        }
+        flat.keep_cpu_data = self.keep_cpu_data;
+        *self = flat;
+        self
+    }
+
+    /// Merge vertices whose attributes are all within `epsilon` of each other.
+    /// Each vertex is merged into the earliest vertex kept so far that is close
+    /// enough to it, or kept if there is none. Unreferenced vertices are
+    /// removed.
+    pub fn weld(&mut self, epsilon: f32) -> &mut Self {
+        let epsilon = epsilon.max(0.0);
+        let cell = |x: f32| {
+            if epsilon > 0.0 {
+                (x / epsilon).floor() as i64
+            } else {
+                // Adding zero turns -0.0 into 0.0, so that both share a cell.
+                (x + 0.0).to_bits() as i64
+            }
+        };
+
+        // Welded vertices are bucketed by position into cells of size `epsilon`,
+        // so any vertex close enough to merge is in a neighbouring cell.
+        let mut welded = MeshBuilder::new();
+        let mut grid: HashMap<[i64; 3], Vec<(u32, [f32; 8])>> = HashMap::new();
+        let mut remap = Vec::with_capacity(self.positions.len());
+        for idx in 0..self.positions.len() {
+            let (p, n, uv) = (self.positions[idx], self.normals[idx], self.uvs[idx]);
+            let attrs = [p.0, p.1, p.2, n.0, n.1, n.2, uv[0], uv[1]];
+            let key = [cell(p.0), cell(p.1), cell(p.2)];
+
+            let mut found: Option<u32> = None;
+            for &dx in &[-1, 0, 1] {
+                for &dy in &[-1, 0, 1] {
+                    for &dz in &[-1, 0, 1] {
+                        let neighbour = [key[0] + dx, key[1] + dy, key[2] + dz];
+                        let close = grid.get(&neighbour).and_then(|vertices| {
+                            vertices
+                                .iter()
+                                .find(|&&(_, other)| within(&attrs, &other, epsilon))
+                                .map(|&(welded_idx, _)| welded_idx)
+                        });
+                        // Each cell is in the order vertices were kept, but the
+                        // cells are not, so the earliest match is kept.
+                        if let Some(close) = close {
+                            found = Some(found.map_or(close, |found| found.min(close)));
+                        }
+                    }
+                }
+            }
+
+            let welded_idx = match found {
+                Some(welded_idx) => welded_idx,
+                None => {
+                    let welded_idx = welded.vertex(p, n, uv);
+                    grid.entry(key).or_insert_with(Vec::new).push((welded_idx, attrs));
+                    welded_idx
+                }
+            };
+            remap.push(welded_idx);
+        }
+        welded.indices = self
+            .indices
+            .iter()
+            .map(|&idx| remap[idx as usize])
+            .collect();
+        welded.remove_unused();
+        welded.keep_cpu_data = self.keep_cpu_data;
+
+        *self = welded;
+        self
+    }
+
+    fn remove_unused(&mut self) {
+        let mut remap = vec![None; self.positions.len()];
+        let mut used = MeshBuilder::new();
+        for idx in &mut self.indices {
+            let old = *idx as usize;
+            let (positions, normals, uvs) = (&self.positions, &self.normals, &self.uvs);
+            *idx = *remap[old]
+                .get_or_insert_with(|| used.vertex(positions[old], normals[old], uvs[old]));
+        }
+        self.positions = used.positions;
+        self.normals = used.normals;
+        self.uvs = used.uvs;
+    }
+
+    fn face_normal(&self, tri: &[u32]) -> Vector {
+        let a = self.positions[tri[0] as usize];
+        let b = self.positions[tri[1] as usize];
+        let c = self.positions[tri[2] as usize];
+        (b - a).cross(c - a)
+    }
+
+    /// An axis-aligned cube centred on the origin.
+    pub fn cube(size: f32) -> Self {
+        const FACES: [(Vector, Vector, Vector); 6] = [
+            (Vector(1.0, 0.0, 0.0), Vector(0.0, 0.0, -1.0), Vector(0.0, 1.0, 0.0)),
+            (Vector(-1.0, 0.0, 0.0), Vector(0.0, 0.0, 1.0), Vector(0.0, 1.0, 0.0)),
+            (Vector(0.0, 1.0, 0.0), Vector(1.0, 0.0, 0.0), Vector(0.0, 0.0, -1.0)),
+            (Vector(0.0, -1.0, 0.0), Vector(1.0, 0.0, 0.0), Vector(0.0, 0.0, 1.0)),
+            (Vector(0.0, 0.0, 1.0), Vector(1.0, 0.0, 0.0), Vector(0.0, 1.0, 0.0)),
+            (Vector(0.0, 0.0, -1.0), Vector(-1.0, 0.0, 0.0), Vector(0.0, 1.0, 0.0)),
+        ];
+
+        let half = size / 2.0;
+        let mut builder = MeshBuilder::new();
+        for &(normal, u, v) in &FACES {
+            let center = normal * half;
+            let corner = |s: f32, t: f32| center + u * (s * half) + v * (t * half);
+            let a = builder.vertex(corner(-1.0, -1.0), normal, [0.0, 1.0]);
+            let b = builder.vertex(corner(1.0, -1.0), normal, [1.0, 1.0]);
+            let c = builder.vertex(corner(1.0, 1.0), normal, [1.0, 0.0]);
+            let d = builder.vertex(corner(-1.0, 1.0), normal, [0.0, 0.0]);
+            builder.quad(a, b, c, d);
+        }
+        builder
+    }
+
+    /// A plane in the XZ plane centred on the origin, facing up the Y axis.
+    pub fn plane(width: f32, depth: f32) -> Self {
+        let (w, d) = (width / 2.0, depth / 2.0);
+        let normal = Vector(0.0, 1.0, 0.0);
+
+        let mut builder = MeshBuilder::new();
+        let a = builder.vertex(Vector(-w, 0.0, d), normal, [0.0, 1.0]);
+        let b = builder.vertex(Vector(w, 0.0, d), normal, [1.0, 1.0]);
+        let c = builder.vertex(Vector(w, 0.0, -d), normal, [1.0, 0.0]);
+        let e = builder.vertex(Vector(-w, 0.0, -d), normal, [0.0, 0.0]);
+        builder.quad(a, b, c, e);
+        builder
+    }
+
+    /// A sphere centred on the origin, divided into `sectors` segments around
+    /// the Y axis and `stacks` segments from pole to pole.
+    pub fn uv_sphere(radius: f32, sectors: u32, stacks: u32) -> Self {
+        assert!(sectors >= 3 && stacks >= 2);
+
+        let mut builder = MeshBuilder::new();
+        for i in 0..stacks + 1 {
+            let v = i as f32 / stacks as f32;
+            let phi = v * PI;
+            for j in 0..sectors + 1 {
+                let u = j as f32 / sectors as f32;
+                let theta = u * 2.0 * PI;
+                let normal = Vector(phi.sin() * theta.cos(), phi.cos(), -phi.sin() * theta.sin());
+                builder.vertex(normal * radius, normal, [u, v]);
+            }
+        }
+
+        let row = sectors + 1;
+        for i in 0..stacks {
+            for j in 0..sectors {
+                let a = i * row + j;
+                let b = a + row;
+                if i != 0 {
+                    builder.triangle(a, b, a + 1);
+                }
+                if i != stacks - 1 {
+                    builder.triangle(a + 1, b, b + 1);
+                }
+            }
+        }
+        builder
+    }
+
+    /// A capped cylinder centred on the origin, with its axis along the Y axis.
+    pub fn cylinder(radius: f32, height: f32, segments: u32) -> Self {
+        assert!(segments >= 3);
+
+        let half = height / 2.0;
+        let up = Vector(0.0, 1.0, 0.0);
+        let mut builder = MeshBuilder::new();
+
+        for j in 0..segments + 1 {
+            let u = j as f32 / segments as f32;
+            let theta = u * 2.0 * PI;
+            let normal = Vector(theta.cos(), 0.0, -theta.sin());
+            let a = builder.vertex(normal * radius - up * half, normal, [u, 1.0]);
+            let b = builder.vertex(normal * radius + up * half, normal, [u, 0.0]);
+            if j != 0 {
+                builder.quad(a - 2, a, b, b - 2);
+            }
+        }
+
+        for &normal in &[up, -up] {
+            let center = builder.vertex(normal * half, normal, [0.5, 0.5]);
+            let first = center + 1;
+            for j in 0..segments {
+                let theta = j as f32 / segments as f32 * 2.0 * PI;
+                let (x, z) = (theta.cos(), -theta.sin());
+                builder.vertex(
+                    Vector(x * radius, 0.0, z * radius) + normal * half,
+                    normal,
+                    [0.5 + x / 2.0, 0.5 - z / 2.0],
+                );
+            }
+            for j in 0..segments {
+                let (a, b) = (first + j, first + (j + 1) % segments);
+                if normal == up {
+                    builder.triangle(center, a, b);
+                } else {
+                    builder.triangle(center, b, a);
+                }
+            }
+        }
+        builder
+    }
+
+    /// Keep copies of the positions and indices in the built `Mesh`, so that it
+    /// can be picked with `PickMode::Triangles`.
+    pub fn keep_cpu_data(&mut self) -> &mut Self {
+        self.keep_cpu_data = true;
+        self
+    }
+
+    /// Upload the mesh on the transfer queue, using `u16` indices if possible.
+    /// See `Mesh::new` for how to wait for the upload.
+    pub fn build(&self, ctx: &Context) -> (Mesh, Box<GpuFuture + Send + Sync>) {
+        let vertices = self
+            .positions
+            .iter()
+            .zip(&self.normals)
+            .zip(&self.uvs)
+            .map(|((&p, &n), &uv)| Vertex::with_uv((p, n, uv)));
+        if self.needs_u32_indices() {
+            Mesh::upload(ctx, vertices, self.indices.iter().cloned(), self.keep_cpu_data)
+        } else {
+            let indices = self.indices.iter().map(|&idx| idx as u16);
+            Mesh::upload(ctx, vertices, indices, self.keep_cpu_data)
+        }
+    }
+}
+
+fn within(lhs: &[f32; 8], rhs: &[f32; 8], epsilon: f32) -> bool {
+    lhs.iter().zip(rhs).all(|(l, r)| (l - r).abs() <= epsilon)
+}
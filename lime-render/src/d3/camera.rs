@@ -0,0 +1,101 @@
+use std::f32::consts::FRAC_PI_3;
+
+use d2::Point;
+use d3::{Matrix, Ray, Sphere, Vector};
+
+/// A perspective camera.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Camera {
+    pub eye: Vector,
+    pub target: Vector,
+    pub up: Vector,
+    /// The vertical field of view, in radians.
+    pub fovy: f32,
+    pub near: f32,
+    pub far: f32,
+}
+
+impl Camera {
+    pub fn new(eye: Vector, target: Vector) -> Self {
+        Camera {
+            eye,
+            target,
+            up: Vector(0.0, 1.0, 0.0),
+            fovy: FRAC_PI_3,
+            near: 0.1,
+            far: 1000.0,
+        }
+    }
+
+    pub fn view(&self) -> Matrix {
+        Matrix::look_at(self.eye, self.target, self.up)
+    }
+
+    pub fn proj(&self, aspect: f32) -> Matrix {
+        Matrix::perspective(self.fovy, aspect, self.near, self.far)
+    }
+
+    pub fn frustum(&self, aspect: f32) -> Frustum {
+        Frustum::new(&(self.proj(aspect) * self.view()))
+    }
+
+    /// The ray from the eye through a point on the screen, such as the cursor
+    /// position tracked by lime-ui. `point` and `size` must use the same units,
+    /// for example logical pixels with the origin at the top left.
+    pub fn ray(&self, point: Point, [width, height]: [f32; 2]) -> Ray {
+        let x = 2.0 * point.0 / width - 1.0;
+        let y = 1.0 - 2.0 * point.1 / height;
+        let tan = (self.fovy / 2.0).tan();
+
+        let forward = (self.target - self.eye).normalize();
+        let right = forward.cross(self.up).normalize();
+        let up = right.cross(forward);
+        let dir = forward + right * (x * tan * width / height) + up * (y * tan);
+        Ray::new(self.eye, dir)
+    }
+}
+
+/// The planes bounding the volume visible through a view-projection matrix.
+#[derive(Copy, Clone, Debug)]
+pub struct Frustum {
+    planes: [[f32; 4]; 6],
+}
+
+impl Frustum {
+    pub fn new(view_proj: &Matrix) -> Self {
+        let r0 = view_proj.row(0);
+        let r1 = view_proj.row(1);
+        let r2 = view_proj.row(2);
+        let r3 = view_proj.row(3);
+
+        let add = |a: [f32; 4], b: [f32; 4]| [a[0] + b[0], a[1] + b[1], a[2] + b[2], a[3] + b[3]];
+        let sub = |a: [f32; 4], b: [f32; 4]| [a[0] - b[0], a[1] - b[1], a[2] - b[2], a[3] - b[3]];
+
+        let mut planes = [
+            add(r3, r0),
+            sub(r3, r0),
+            add(r3, r1),
+            sub(r3, r1),
+            r2,
+            sub(r3, r2),
+        ];
+        for plane in &mut planes {
+            let len = Vector(plane[0], plane[1], plane[2]).length();
+            if len > 0.0 {
+                for x in plane.iter_mut() {
+                    *x /= len;
+                }
+            }
+        }
+
+        Frustum { planes }
+    }
+
+    /// Whether any part of the sphere may be inside the frustum.
+    pub fn intersects_sphere(&self, sphere: &Sphere) -> bool {
+        let Vector(x, y, z) = sphere.center;
+        self.planes
+            .iter()
+            .all(|p| p[0] * x + p[1] * y + p[2] * z + p[3] >= -sphere.radius)
+    }
+}
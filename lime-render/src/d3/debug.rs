@@ -0,0 +1,369 @@
+use std::f32::consts::PI;
+use std::mem;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use failure::Fallible;
+use vulkano::buffer::CpuBufferPool;
+use vulkano::command_buffer::{AutoCommandBufferBuilder, DynamicState};
+use vulkano::descriptor::descriptor_set::FixedSizeDescriptorSetsPool;
+use vulkano::descriptor::PipelineLayoutAbstract;
+use vulkano::device::Device;
+use vulkano::framebuffer::{RenderPassAbstract, Subpass};
+use vulkano::pipeline::depth_stencil::DepthStencil;
+use vulkano::pipeline::vertex::SingleBufferDefinition;
+use vulkano::pipeline::GraphicsPipeline;
+
+use d3::{Aabb, Matrix, Sphere, Vector};
+use {Color, RenderStats};
+
+/// The number of line segments used to draw a circle.
+const CIRCLE_SEGMENTS: usize = 32;
+
+type Pipeline = Arc<
+    GraphicsPipeline<
+        SingleBufferDefinition<Vertex>,
+        Box<PipelineLayoutAbstract + Send + Sync>,
+        Arc<RenderPassAbstract + Send + Sync>,
+    >,
+>;
+
+/// Immediate-mode debug drawing in world space.
+///
+/// Lines are drawn over the scene with the d3 camera. By default they are
+/// depth tested against meshes and last for one frame; `with_depth_test` and
+/// `with_duration` change this for everything drawn inside them.
+#[derive(Clone, Debug)]
+pub struct Debug {
+    lines: Vec<DebugLine>,
+    depth_test: bool,
+    duration: Option<Duration>,
+}
+
+/// A line queued on `Debug`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct DebugLine {
+    pub start: Vector,
+    pub end: Vector,
+    pub color: Color,
+    pub depth_test: bool,
+    expires: Option<Instant>,
+}
+
+impl Debug {
+    pub fn new() -> Self {
+        Debug {
+            lines: Vec::new(),
+            depth_test: true,
+            duration: None,
+        }
+    }
+
+    /// The lines that will be drawn in the next frame.
+    pub fn lines(&self) -> &[DebugLine] {
+        &self.lines
+    }
+
+    /// Remove every line, including those that have not expired yet.
+    pub fn clear(&mut self) {
+        self.lines.clear();
+    }
+
+    /// Draw everything queued by `f` with or without depth testing.
+    pub fn with_depth_test<F, R>(&mut self, depth_test: bool, f: F) -> R
+    where
+        F: FnOnce(&mut Self) -> R,
+    {
+        let prev = mem::replace(&mut self.depth_test, depth_test);
+        let result = f(self);
+        self.depth_test = prev;
+        result
+    }
+
+    /// Keep everything queued by `f` on screen for `duration` instead of a
+    /// single frame.
+    pub fn with_duration<F, R>(&mut self, duration: Duration, f: F) -> R
+    where
+        F: FnOnce(&mut Self) -> R,
+    {
+        let prev = mem::replace(&mut self.duration, Some(duration));
+        let result = f(self);
+        self.duration = prev;
+        result
+    }
+
+    pub fn line(&mut self, start: Vector, end: Vector, color: Color) {
+        let expires = self.duration.map(|duration| Instant::now() + duration);
+        self.lines.push(DebugLine {
+            start,
+            end,
+            color,
+            depth_test: self.depth_test,
+            expires,
+        });
+    }
+
+    /// Draw a line with an arrow head at `to`.
+    pub fn arrow(&mut self, from: Vector, to: Vector, color: Color) {
+        self.line(from, to, color);
+
+        let dir = to - from;
+        let len = dir.length();
+        if len == 0.0 {
+            return;
+        }
+        let dir = dir * (1.0 / len);
+        let (u, v) = basis(dir);
+        let head = len * 0.2;
+        let base = to - dir * head;
+        for &side in &[u, -u, v, -v] {
+            self.line(to, base + side * (head * 0.5), color);
+        }
+    }
+
+    /// Draw the edges of a box.
+    pub fn wire_box(&mut self, aabb: &Aabb, color: Color) {
+        let corners = aabb.corners();
+        for i in 0..8 {
+            for &bit in &[1, 2, 4] {
+                if i & bit == 0 {
+                    self.line(corners[i], corners[i | bit], color);
+                }
+            }
+        }
+    }
+
+    /// Draw a sphere as three circles around its axes.
+    pub fn sphere(&mut self, sphere: &Sphere, color: Color) {
+        let x = Vector(sphere.radius, 0.0, 0.0);
+        let y = Vector(0.0, sphere.radius, 0.0);
+        let z = Vector(0.0, 0.0, sphere.radius);
+        self.ellipse(sphere.center, x, y, color);
+        self.ellipse(sphere.center, y, z, color);
+        self.ellipse(sphere.center, z, x, color);
+    }
+
+    /// Draw a square grid on the XZ plane, divided into `divisions` cells along
+    /// each side.
+    pub fn grid(&mut self, center: Vector, size: f32, divisions: u32, color: Color) {
+        let divisions = divisions.max(1);
+        let half = size / 2.0;
+        for i in 0..=divisions {
+            let offset = size * i as f32 / divisions as f32 - half;
+            self.line(
+                center + Vector(offset, 0.0, -half),
+                center + Vector(offset, 0.0, half),
+                color,
+            );
+            self.line(
+                center + Vector(-half, 0.0, offset),
+                center + Vector(half, 0.0, offset),
+                color,
+            );
+        }
+    }
+
+    /// Draw the axes of a transform, with x in red, y in green and z in blue.
+    pub fn axes(&mut self, transform: &Matrix, size: f32) {
+        let origin = transform.transform_point(Vector::origin());
+        let axes = [
+            (Vector(size, 0.0, 0.0), Color::RED),
+            (Vector(0.0, size, 0.0), Color::GREEN),
+            (Vector(0.0, 0.0, size), Color::BLUE),
+        ];
+        for &(axis, color) in &axes {
+            self.arrow(origin, origin + transform.transform_vector(axis), color);
+        }
+    }
+
+    fn ellipse(&mut self, center: Vector, u: Vector, v: Vector, color: Color) {
+        let point = |i: usize| {
+            let (sin, cos) = (2.0 * PI * i as f32 / CIRCLE_SEGMENTS as f32).sin_cos();
+            center + u * cos + v * sin
+        };
+        for i in 0..CIRCLE_SEGMENTS {
+            self.line(point(i), point(i + 1), color);
+        }
+    }
+
+    /// Remove lines that should not be drawn again after `now`.
+    fn retain(&mut self, now: Instant) {
+        self.lines
+            .retain(|line| line.expires.map(|expires| expires > now).unwrap_or(false));
+    }
+}
+
+impl Default for Debug {
+    fn default() -> Self {
+        Debug::new()
+    }
+}
+
+/// Two unit vectors perpendicular to `dir` and each other.
+fn basis(dir: Vector) -> (Vector, Vector) {
+    let other = if dir.0.abs() < 0.9 {
+        Vector(1.0, 0.0, 0.0)
+    } else {
+        Vector(0.0, 1.0, 0.0)
+    };
+    let u = dir.cross(other).normalize();
+    (u, dir.cross(u))
+}
+
+/// Draws the lines queued on `Debug`.
+pub(in d3) struct DebugPipeline {
+    depth_tested: Pipeline,
+    overlay: Pipeline,
+    vbuf: CpuBufferPool<Vertex>,
+    ubuf: CpuBufferPool<vs::ty::Data>,
+    pool: FixedSizeDescriptorSetsPool<Pipeline>,
+}
+
+impl DebugPipeline {
+    pub(in d3) fn new(
+        device: &Arc<Device>,
+        subpass: Subpass<Arc<RenderPassAbstract + Send + Sync>>,
+    ) -> Fallible<Self> {
+        let vs = vs::Shader::load(Arc::clone(device))?;
+        let fs = fs::Shader::load(Arc::clone(device))?;
+
+        let create = |depth_stencil: DepthStencil| -> Fallible<Pipeline> {
+            Ok(Arc::new(
+                GraphicsPipeline::start()
+                    .vertex_input_single_buffer::<Vertex>()
+                    .vertex_shader(vs.main_entry_point(), ())
+                    .line_list()
+                    .viewports_dynamic_scissors_irrelevant(1)
+                    .fragment_shader(fs.main_entry_point(), ())
+                    .depth_stencil(depth_stencil)
+                    .blend_alpha_blending()
+                    .render_pass(subpass.clone())
+                    .build(Arc::clone(device))?,
+            ))
+        };
+        let depth_tested = create(DepthStencil {
+            depth_write: false,
+            ..DepthStencil::simple_depth_test()
+        })?;
+        let overlay = create(DepthStencil::disabled())?;
+
+        let pool = FixedSizeDescriptorSetsPool::new(Arc::clone(&depth_tested), 0);
+        Ok(DebugPipeline {
+            depth_tested,
+            overlay,
+            vbuf: CpuBufferPool::vertex_buffer(Arc::clone(device)),
+            ubuf: CpuBufferPool::uniform_buffer(Arc::clone(device)),
+            pool,
+        })
+    }
+
+    /// Draw the lines queued on `debug`, then remove those that have expired.
+    pub(in d3) fn commit(
+        &mut self,
+        mut cmd: AutoCommandBufferBuilder,
+        state: &DynamicState,
+        debug: &mut Debug,
+        view_proj: Matrix,
+        stats: &mut RenderStats,
+    ) -> Fallible<AutoCommandBufferBuilder> {
+        if !debug.lines.is_empty() {
+            let ubuf = self.ubuf.next(vs::ty::Data {
+                view_proj: view_proj.into(),
+            })?;
+            let set = Arc::new(self.pool.next().add_buffer(ubuf)?.build()?);
+            stats.uploaded_bytes += mem::size_of::<vs::ty::Data>();
+
+            for &depth_test in &[true, false] {
+                let mut vertices = Vec::new();
+                for line in &debug.lines {
+                    if line.depth_test == depth_test {
+                        vertices.push(Vertex::new(line.start, line.color));
+                        vertices.push(Vertex::new(line.end, line.color));
+                    }
+                }
+                if vertices.is_empty() {
+                    continue;
+                }
+
+                stats.draw_calls += 1;
+                stats.vertices += vertices.len();
+                stats.uploaded_bytes += vertices.len() * mem::size_of::<Vertex>();
+
+                let pipe = if depth_test {
+                    &self.depth_tested
+                } else {
+                    &self.overlay
+                };
+                let vbuf = self.vbuf.chunk(vertices)?;
+                cmd = cmd.draw(Arc::clone(pipe), state, vbuf, Arc::clone(&set), ())?;
+            }
+        }
+
+        debug.retain(Instant::now());
+        Ok(cmd)
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+struct Vertex {
+    position: Vector,
+    color: Color,
+}
+
+impl Vertex {
+    fn new(position: Vector, color: Color) -> Self {
+        Vertex { position, color }
+    }
+}
+
+impl_vertex!(Vertex, position, color);
+
+#[allow(unused)]
+mod vs {
+    #[derive(VulkanoShader)]
+    #[ty = "vertex"]
+    #[path = "shader/d3/debug/vert.glsl"]
+    struct Dummy;
+}
+
+#[allow(unused)]
+mod fs {
+    #[derive(VulkanoShader)]
+    #[ty = "fragment"]
+    #[path = "shader/d3/debug/frag.glsl"]
+    struct Dummy;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn one_frame() {
+        let mut debug = Debug::new();
+        debug.line(Vector::origin(), Vector(1.0, 0.0, 0.0), Color::WHITE);
+        assert_eq!(debug.lines().len(), 1);
+
+        debug.retain(Instant::now());
+        assert!(debug.lines().is_empty());
+    }
+
+    #[test]
+    fn duration() {
+        let duration = Duration::from_secs(1);
+        let start = Instant::now();
+        let mut debug = Debug::new();
+        debug.with_duration(duration, |debug| {
+            debug.line(Vector::origin(), Vector(1.0, 0.0, 0.0), Color::WHITE);
+        });
+        debug.line(Vector::origin(), Vector(0.0, 1.0, 0.0), Color::WHITE);
+
+        // Only the one-frame line is removed after the first frame.
+        debug.retain(start);
+        assert_eq!(debug.lines().len(), 1);
+        assert_eq!(debug.lines()[0].end, Vector(1.0, 0.0, 0.0));
+
+        debug.retain(Instant::now() + duration);
+        assert!(debug.lines().is_empty());
+    }
+}
@@ -1,3 +1,5 @@
+use std::ops::{Add, AddAssign, Mul, Neg, Sub};
+
 use vulkano::pipeline::vertex::{VertexMember, VertexMemberTy};
 
 #[repr(C)]
@@ -8,6 +10,33 @@ impl Vector {
     pub fn origin() -> Self {
         Vector(0.0, 0.0, 0.0)
     }
+
+    pub fn dot(self, other: Vector) -> f32 {
+        self.0 * other.0 + self.1 * other.1 + self.2 * other.2
+    }
+
+    pub fn cross(self, other: Vector) -> Vector {
+        Vector(
+            self.1 * other.2 - self.2 * other.1,
+            self.2 * other.0 - self.0 * other.2,
+            self.0 * other.1 - self.1 * other.0,
+        )
+    }
+
+    pub fn length(self) -> f32 {
+        self.dot(self).sqrt()
+    }
+
+    /// Get a unit vector in the same direction, or the zero vector if this
+    /// vector has zero length.
+    pub fn normalize(self) -> Vector {
+        let len = self.length();
+        if len > 0.0 {
+            self * (1.0 / len)
+        } else {
+            Vector::origin()
+        }
+    }
 }
 
 impl PartialEq for Vector {
@@ -16,6 +45,44 @@ impl PartialEq for Vector {
     }
 }
 
+impl Add for Vector {
+    type Output = Vector;
+
+    fn add(self, other: Vector) -> Vector {
+        Vector(self.0 + other.0, self.1 + other.1, self.2 + other.2)
+    }
+}
+
+impl AddAssign for Vector {
+    fn add_assign(&mut self, other: Vector) {
+        *self = *self + other;
+    }
+}
+
+impl Sub for Vector {
+    type Output = Vector;
+
+    fn sub(self, other: Vector) -> Vector {
+        Vector(self.0 - other.0, self.1 - other.1, self.2 - other.2)
+    }
+}
+
+impl Mul<f32> for Vector {
+    type Output = Vector;
+
+    fn mul(self, scale: f32) -> Vector {
+        Vector(self.0 * scale, self.1 * scale, self.2 * scale)
+    }
+}
+
+impl Neg for Vector {
+    type Output = Vector;
+
+    fn neg(self) -> Vector {
+        Vector(-self.0, -self.1, -self.2)
+    }
+}
+
 unsafe impl VertexMember for Vector {
     fn format() -> (VertexMemberTy, usize) {
         (VertexMemberTy::F32, 3)
@@ -0,0 +1,29 @@
+use Color;
+
+pub(in d3) const IDENTITY: [[f32; 4]; 4] = [
+    [1.0, 0.0, 0.0, 0.0],
+    [0.0, 1.0, 0.0, 0.0],
+    [0.0, 0.0, 1.0, 0.0],
+    [0.0, 0.0, 0.0, 1.0],
+];
+
+/// Per-instance data for a mesh draw: a column-major world transform and a
+/// colour.
+#[repr(C)]
+#[derive(Copy, Clone, Debug)]
+pub struct InstanceData {
+    pub transform: [[f32; 4]; 4],
+    pub color: Color,
+}
+
+impl InstanceData {
+    pub fn new(transform: [[f32; 4]; 4], color: Color) -> Self {
+        InstanceData { transform, color }
+    }
+
+    pub fn identity(color: Color) -> Self {
+        InstanceData::new(IDENTITY, color)
+    }
+}
+
+impl_vertex!(InstanceData, transform, color);
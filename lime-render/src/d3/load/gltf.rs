@@ -0,0 +1,66 @@
+use std::path::Path;
+
+use gltf;
+use gltf::mesh::Mode;
+
+use d3::load::{validate, LoadError};
+use d3::{MeshBuilder, Vector};
+
+pub(in d3::load) fn load(path: &Path) -> Result<Vec<MeshBuilder>, LoadError> {
+    let (doc, buffers, _) = gltf::import(path).map_err(|err| LoadError::Gltf(err.to_string()))?;
+
+    if let Some(ext) = doc.extensions_required().next() {
+        return Err(LoadError::Unsupported(format!("glTF extension '{}'", ext)));
+    }
+
+    let mut prims = Vec::new();
+    for mesh in doc.meshes() {
+        for prim in mesh.primitives() {
+            if prim.mode() != Mode::Triangles {
+                return Err(LoadError::Unsupported(format!(
+                    "primitive mode {:?}",
+                    prim.mode()
+                )));
+            }
+            for (semantic, accessor) in prim.attributes() {
+                if accessor.sparse().is_some() {
+                    return Err(LoadError::Unsupported(format!(
+                        "sparse accessor for {:?}",
+                        semantic
+                    )));
+                }
+            }
+
+            let reader = prim.reader(|buffer| Some(&buffers[buffer.index()]));
+
+            let positions: Vec<Vector> = reader
+                .read_positions()
+                .ok_or(LoadError::MissingPositions)?
+                .map(|[x, y, z]| Vector(x, y, z))
+                .collect();
+            let normals: Vec<Vector> = match reader.read_normals() {
+                Some(normals) => normals.map(|[x, y, z]| Vector(x, y, z)).collect(),
+                None => Vec::new(),
+            };
+            if !normals.is_empty() && normals.len() != positions.len() {
+                return Err(LoadError::InvalidAttribute("NORMAL"));
+            }
+            let uvs: Vec<[f32; 2]> = match reader.read_tex_coords(0) {
+                Some(uvs) => uvs.into_f32().collect(),
+                None => Vec::new(),
+            };
+            if !uvs.is_empty() && uvs.len() != positions.len() {
+                return Err(LoadError::InvalidAttribute("TEXCOORD_0"));
+            }
+            let indices = match reader.read_indices() {
+                Some(indices) => indices.into_u32().collect(),
+                None => (0..positions.len() as u32).collect(),
+            };
+
+            let has_normals = !normals.is_empty();
+            let builder = MeshBuilder::from_parts(positions, normals, uvs, indices);
+            prims.push(validate(builder, has_normals)?);
+        }
+    }
+    Ok(prims)
+}
@@ -0,0 +1,92 @@
+mod gltf;
+mod obj;
+#[cfg(test)]
+mod tests;
+
+use std::error::Error;
+use std::fmt;
+use std::path::Path;
+use std::sync::Arc;
+
+use failure::Fallible;
+use vulkano::sync::{now, GpuFuture};
+
+use d3::{Mesh, MeshBuilder};
+use Context;
+
+/// Load every mesh in a Wavefront OBJ file. Each object or group in the file
+/// becomes a separate `Mesh`. Smooth normals are generated for meshes without
+/// normals.
+pub fn load_obj(
+    ctx: &Context,
+    path: impl AsRef<Path>,
+) -> Fallible<(Vec<Mesh>, Box<GpuFuture + Send + Sync>)> {
+    upload(ctx, obj::load(path.as_ref())?)
+}
+
+/// Load every mesh primitive in a glTF 2.0 file. Both `.gltf` files with
+/// embedded or separate buffers and binary `.glb` files are supported. Node
+/// transforms are not applied. Smooth normals are generated for primitives
+/// without normals.
+pub fn load_gltf(
+    ctx: &Context,
+    path: impl AsRef<Path>,
+) -> Fallible<(Vec<Mesh>, Box<GpuFuture + Send + Sync>)> {
+    upload(ctx, gltf::load(path.as_ref())?)
+}
+
+/// Check that mesh data read from a file is consistent, and generate normals
+/// if the file did not provide them.
+fn validate(mut builder: MeshBuilder, has_normals: bool) -> Result<MeshBuilder, LoadError> {
+    let len = builder.positions().len();
+    if builder.indices().len() % 3 != 0 || builder.indices().iter().any(|&i| i as usize >= len) {
+        return Err(LoadError::InvalidIndices);
+    }
+    if !has_normals {
+        builder.smooth_normals();
+    }
+    Ok(builder)
+}
+
+fn upload(
+    ctx: &Context,
+    builders: Vec<MeshBuilder>,
+) -> Fallible<(Vec<Mesh>, Box<GpuFuture + Send + Sync>)> {
+    let mut meshes = Vec::with_capacity(builders.len());
+    let mut future: Box<GpuFuture + Send + Sync> = Box::new(now(Arc::clone(ctx.device())));
+    for builder in builders {
+        let (mesh, mesh_future) = builder.build(ctx);
+        meshes.push(mesh);
+        future = Box::new(future.join(mesh_future));
+    }
+    Ok((meshes, future))
+}
+
+#[derive(Debug)]
+pub enum LoadError {
+    Obj(String),
+    Gltf(String),
+    MissingPositions,
+    InvalidAttribute(&'static str),
+    InvalidIndices,
+    Unsupported(String),
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LoadError::Obj(err) => write!(f, "failed to read OBJ file: {}", err),
+            LoadError::Gltf(err) => write!(f, "failed to read glTF file: {}", err),
+            LoadError::MissingPositions => write!(f, "mesh has no vertex positions"),
+            LoadError::InvalidAttribute(name) => {
+                write!(f, "vertex attribute '{}' has the wrong length", name)
+            }
+            LoadError::InvalidIndices => {
+                write!(f, "mesh indices do not form a valid triangle list")
+            }
+            LoadError::Unsupported(feature) => write!(f, "unsupported feature: {}", feature),
+        }
+    }
+}
+
+impl Error for LoadError {}
@@ -0,0 +1,41 @@
+use std::path::Path;
+
+use tobj;
+
+use d3::load::{validate, LoadError};
+use d3::{MeshBuilder, Vector};
+
+pub(in d3::load) fn load(path: &Path) -> Result<Vec<MeshBuilder>, LoadError> {
+    let (models, _) = tobj::load_obj(path).map_err(|err| LoadError::Obj(err.to_string()))?;
+
+    models
+        .into_iter()
+        .map(|model| {
+            let mesh = model.mesh;
+            if mesh.positions.is_empty() {
+                return Err(LoadError::MissingPositions);
+            }
+            let has_normals = !mesh.normals.is_empty();
+            if has_normals && mesh.normals.len() != mesh.positions.len() {
+                return Err(LoadError::InvalidAttribute("vn"));
+            }
+            if !mesh.texcoords.is_empty() && mesh.texcoords.len() / 2 != mesh.positions.len() / 3 {
+                return Err(LoadError::InvalidAttribute("vt"));
+            }
+
+            let builder = MeshBuilder::from_parts(
+                mesh.positions
+                    .chunks(3)
+                    .map(|p| Vector(p[0], p[1], p[2]))
+                    .collect(),
+                mesh.normals
+                    .chunks(3)
+                    .map(|n| Vector(n[0], n[1], n[2]))
+                    .collect(),
+                mesh.texcoords.chunks(2).map(|t| [t[0], t[1]]).collect(),
+                mesh.indices,
+            );
+            validate(builder, has_normals)
+        })
+        .collect()
+}
@@ -0,0 +1,77 @@
+use std::path::PathBuf;
+
+use super::*;
+use d3::Vector;
+
+fn data_path(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("data")
+        .join("load")
+        .join(name)
+}
+
+#[test]
+fn obj() {
+    let meshes = obj::load(&data_path("quads.obj")).unwrap();
+    assert_eq!(meshes.len(), 2);
+    for mesh in &meshes {
+        assert_eq!(mesh.positions().len(), 4);
+        assert_eq!(mesh.indices().len(), 6);
+        assert_eq!(mesh.uvs()[2], [1.0, 1.0]);
+        for &normal in mesh.normals() {
+            assert_eq!(normal, Vector(0.0, 0.0, 1.0));
+        }
+    }
+}
+
+#[test]
+fn gltf() {
+    let meshes = gltf::load(&data_path("triangle.gltf")).unwrap();
+    assert_eq!(meshes.len(), 1);
+    assert_eq!(
+        meshes[0].positions(),
+        &[
+            Vector(0.0, 0.0, 0.0),
+            Vector(1.0, 0.0, 0.0),
+            Vector(0.0, 1.0, 0.0),
+        ]
+    );
+    assert_eq!(meshes[0].indices(), &[0, 1, 2]);
+    for &normal in meshes[0].normals() {
+        assert_eq!(normal, Vector(0.0, 0.0, 1.0));
+    }
+}
+
+#[test]
+fn missing_file() {
+    match obj::load(&data_path("missing.obj")) {
+        Err(LoadError::Obj(_)) => (),
+        res => panic!("unexpected result {:?}", res),
+    }
+    match gltf::load(&data_path("missing.gltf")) {
+        Err(LoadError::Gltf(_)) => (),
+        res => panic!("unexpected result {:?}", res),
+    }
+}
+
+#[test]
+fn invalid_indices() {
+    let positions = vec![Vector::origin(); 3];
+    let builder = MeshBuilder::from_parts(positions.clone(), vec![], vec![], vec![0, 1]);
+    assert!(validate(builder, false).is_err());
+    let builder = MeshBuilder::from_parts(positions, vec![], vec![], vec![0, 1, 3]);
+    assert!(validate(builder, false).is_err());
+}
+
+#[test]
+fn display() {
+    assert_eq!(
+        LoadError::Obj("no such file".to_owned()).to_string(),
+        "failed to read OBJ file: no such file"
+    );
+    assert_eq!(
+        LoadError::InvalidIndices.to_string(),
+        "mesh indices do not form a valid triangle list"
+    );
+}
@@ -0,0 +1,190 @@
+use std::ops::Mul;
+
+use d3::Vector;
+
+/// A column-major 4x4 matrix, laid out as expected by the shaders.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Matrix(pub [[f32; 4]; 4]);
+
+impl Matrix {
+    pub fn identity() -> Self {
+        Matrix::scale(Vector(1.0, 1.0, 1.0))
+    }
+
+    pub fn translation(offset: Vector) -> Self {
+        Matrix([
+            [1.0, 0.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [offset.0, offset.1, offset.2, 1.0],
+        ])
+    }
+
+    pub fn scale(scale: Vector) -> Self {
+        Matrix([
+            [scale.0, 0.0, 0.0, 0.0],
+            [0.0, scale.1, 0.0, 0.0],
+            [0.0, 0.0, scale.2, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    /// A rotation of `angle` radians counter-clockwise around `axis`.
+    pub fn rotation(axis: Vector, angle: f32) -> Self {
+        let Vector(x, y, z) = axis.normalize();
+        let (sin, cos) = angle.sin_cos();
+        let t = 1.0 - cos;
+        Matrix([
+            [t * x * x + cos, t * x * y + sin * z, t * x * z - sin * y, 0.0],
+            [t * x * y - sin * z, t * y * y + cos, t * y * z + sin * x, 0.0],
+            [t * x * z + sin * y, t * y * z - sin * x, t * z * z + cos, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    /// A right-handed view matrix for a camera at `eye` looking towards
+    /// `target`.
+    pub fn look_at(eye: Vector, target: Vector, up: Vector) -> Self {
+        let f = (target - eye).normalize();
+        let s = f.cross(up).normalize();
+        let u = s.cross(f);
+        Matrix([
+            [s.0, u.0, -f.0, 0.0],
+            [s.1, u.1, -f.1, 0.0],
+            [s.2, u.2, -f.2, 0.0],
+            [-s.dot(eye), -u.dot(eye), f.dot(eye), 1.0],
+        ])
+    }
+
+    /// A perspective projection into Vulkan clip space, where y points down and
+    /// depth ranges from 0 at the near plane to 1 at the far plane.
+    pub fn perspective(fovy: f32, aspect: f32, near: f32, far: f32) -> Self {
+        let f = 1.0 / (fovy / 2.0).tan();
+        Matrix([
+            [f / aspect, 0.0, 0.0, 0.0],
+            [0.0, -f, 0.0, 0.0],
+            [0.0, 0.0, far / (near - far), -1.0],
+            [0.0, 0.0, near * far / (near - far), 0.0],
+        ])
+    }
+
+    pub fn column(&self, idx: usize) -> [f32; 4] {
+        self.0[idx]
+    }
+
+    pub fn row(&self, idx: usize) -> [f32; 4] {
+        let m = &self.0;
+        [m[0][idx], m[1][idx], m[2][idx], m[3][idx]]
+    }
+
+    /// Transform a point, including translation and perspective division.
+    pub fn transform_point(&self, point: Vector) -> Vector {
+        let [x, y, z, w] = self.transform([point.0, point.1, point.2, 1.0]);
+        Vector(x / w, y / w, z / w)
+    }
+
+    /// Transform a direction, ignoring translation.
+    pub fn transform_vector(&self, vector: Vector) -> Vector {
+        let [x, y, z, _] = self.transform([vector.0, vector.1, vector.2, 0.0]);
+        Vector(x, y, z)
+    }
+
+    pub fn transform(&self, v: [f32; 4]) -> [f32; 4] {
+        let m = &self.0;
+        let mut out = [0.0; 4];
+        for (row, out) in out.iter_mut().enumerate() {
+            *out = m[0][row] * v[0] + m[1][row] * v[1] + m[2][row] * v[2] + m[3][row] * v[3];
+        }
+        out
+    }
+
+    /// The inverse of the matrix, or `None` if it cannot be inverted.
+    pub fn inverse(&self) -> Option<Matrix> {
+        // Cofactor expansion using the 2x2 minors of the first two and the last
+        // two columns.
+        let [a00, a01, a02, a03] = self.0[0];
+        let [a10, a11, a12, a13] = self.0[1];
+        let [a20, a21, a22, a23] = self.0[2];
+        let [a30, a31, a32, a33] = self.0[3];
+        let s0 = a00 * a11 - a10 * a01;
+        let s1 = a00 * a12 - a10 * a02;
+        let s2 = a00 * a13 - a10 * a03;
+        let s3 = a01 * a12 - a11 * a02;
+        let s4 = a01 * a13 - a11 * a03;
+        let s5 = a02 * a13 - a12 * a03;
+        let c0 = a20 * a31 - a30 * a21;
+        let c1 = a20 * a32 - a30 * a22;
+        let c2 = a20 * a33 - a30 * a23;
+        let c3 = a21 * a32 - a31 * a22;
+        let c4 = a21 * a33 - a31 * a23;
+        let c5 = a22 * a33 - a32 * a23;
+
+        let det = s0 * c5 - s1 * c4 + s2 * c3 + s3 * c2 - s4 * c1 + s5 * c0;
+        if det == 0.0 {
+            return None;
+        }
+        let inv = 1.0 / det;
+        Some(Matrix([
+            [
+                (a11 * c5 - a12 * c4 + a13 * c3) * inv,
+                (-a01 * c5 + a02 * c4 - a03 * c3) * inv,
+                (a31 * s5 - a32 * s4 + a33 * s3) * inv,
+                (-a21 * s5 + a22 * s4 - a23 * s3) * inv,
+            ],
+            [
+                (-a10 * c5 + a12 * c2 - a13 * c1) * inv,
+                (a00 * c5 - a02 * c2 + a03 * c1) * inv,
+                (-a30 * s5 + a32 * s2 - a33 * s1) * inv,
+                (a20 * s5 - a22 * s2 + a23 * s1) * inv,
+            ],
+            [
+                (a10 * c4 - a11 * c2 + a13 * c0) * inv,
+                (-a00 * c4 + a01 * c2 - a03 * c0) * inv,
+                (a30 * s4 - a31 * s2 + a33 * s0) * inv,
+                (-a20 * s4 + a21 * s2 - a23 * s0) * inv,
+            ],
+            [
+                (-a10 * c3 + a11 * c1 - a12 * c0) * inv,
+                (a00 * c3 - a01 * c1 + a02 * c0) * inv,
+                (-a30 * s3 + a31 * s1 - a32 * s0) * inv,
+                (a20 * s3 - a21 * s1 + a22 * s0) * inv,
+            ],
+        ]))
+    }
+
+    /// The largest factor by which this matrix scales lengths along any axis.
+    pub fn max_scale(&self) -> f32 {
+        let len = |c: [f32; 4]| Vector(c[0], c[1], c[2]).length();
+        len(self.0[0]).max(len(self.0[1])).max(len(self.0[2]))
+    }
+}
+
+impl Default for Matrix {
+    fn default() -> Self {
+        Matrix::identity()
+    }
+}
+
+impl Mul for Matrix {
+    type Output = Matrix;
+
+    fn mul(self, rhs: Matrix) -> Matrix {
+        let mut out = [[0.0; 4]; 4];
+        for (col, out) in out.iter_mut().enumerate() {
+            *out = self.transform(rhs.0[col]);
+        }
+        Matrix(out)
+    }
+}
+
+impl From<[[f32; 4]; 4]> for Matrix {
+    fn from(m: [[f32; 4]; 4]) -> Self {
+        Matrix(m)
+    }
+}
+
+impl From<Matrix> for [[f32; 4]; 4] {
+    fn from(m: Matrix) -> Self {
+        m.0
+    }
+}
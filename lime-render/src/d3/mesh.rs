@@ -1,38 +1,209 @@
 use std::sync::Arc;
 
 use utils::throw;
-use vulkano::buffer::{BufferUsage, ImmutableBuffer};
+use vulkano::buffer::{BufferUsage, ImmutableBuffer, TypedBufferAccess};
+use vulkano::pipeline::input_assembly::Index;
 use vulkano::sync::GpuFuture;
 
-use d3::{Vector, Vertex};
-use Context;
+use d3::{Aabb, Sphere, Vector, Vertex};
+use {AssetId, Context};
 
 #[derive(Clone)]
 pub struct Mesh {
     pub(in d3) vertices: Arc<ImmutableBuffer<[Vertex]>>,
-    pub(in d3) indices: Arc<ImmutableBuffer<[u16]>>,
+    pub(in d3) indices: Indices,
+    cpu_data: Option<CpuData>,
+    aabb: Aabb,
+    sphere: Sphere,
+    asset: Option<AssetId>,
+}
+
+/// Copies of the vertex positions and indices, kept for picking.
+#[derive(Clone)]
+struct CpuData {
+    positions: Arc<[Vector]>,
+    indices: Arc<[u32]>,
+}
+
+#[derive(Clone)]
+pub(in d3) enum Indices {
+    U16(Arc<ImmutableBuffer<[u16]>>),
+    U32(Arc<ImmutableBuffer<[u32]>>),
+}
+
+impl Indices {
+    pub(in d3) fn len(&self) -> usize {
+        match self {
+            Indices::U16(buf) => buf.len(),
+            Indices::U32(buf) => buf.len(),
+        }
+    }
+}
+
+pub(in d3) trait IndexFormat: Index + Copy + Send + Sync + 'static {
+    fn wrap(buf: Arc<ImmutableBuffer<[Self]>>) -> Indices;
+    fn to_u32(self) -> u32;
+}
+
+impl IndexFormat for u16 {
+    fn wrap(buf: Arc<ImmutableBuffer<[Self]>>) -> Indices {
+        Indices::U16(buf)
+    }
+
+    fn to_u32(self) -> u32 {
+        u32::from(self)
+    }
+}
+
+impl IndexFormat for u32 {
+    fn wrap(buf: Arc<ImmutableBuffer<[Self]>>) -> Indices {
+        Indices::U32(buf)
+    }
+
+    fn to_u32(self) -> u32 {
+        self
+    }
 }
 
 impl Mesh {
-    pub fn new<V, I>(ctx: &Context, vertices: V, indices: I) -> (Self, Box<GpuFuture>)
+    /// Upload a mesh on the transfer queue. The upload must finish before the
+    /// mesh is drawn, which can be handled by passing the result to
+    /// `Uploads::track_mesh`.
+    pub fn new<V, I>(ctx: &Context, vertices: V, indices: I) -> (Self, Box<GpuFuture + Send + Sync>)
     where
         V: IntoIterator<Item = (Vector, Vector)>,
         V::IntoIter: ExactSizeIterator,
         I: IntoIterator<Item = u16>,
         I::IntoIter: ExactSizeIterator,
     {
+        Mesh::upload(ctx, vertices.into_iter().map(Vertex::new), indices, false)
+    }
+
+    /// Create a mesh with texture coordinates for each vertex.
+    pub fn with_uvs<V, I>(
+        ctx: &Context,
+        vertices: V,
+        indices: I,
+    ) -> (Self, Box<GpuFuture + Send + Sync>)
+    where
+        V: IntoIterator<Item = (Vector, Vector, [f32; 2])>,
+        V::IntoIter: ExactSizeIterator,
+        I: IntoIterator<Item = u16>,
+        I::IntoIter: ExactSizeIterator,
+    {
+        Mesh::upload(ctx, vertices.into_iter().map(Vertex::with_uv), indices, false)
+    }
+
+    /// Upload a mesh, keeping copies of its positions and indices if
+    /// `keep_cpu_data` is set.
+    pub(in d3) fn upload<V, I>(
+        ctx: &Context,
+        vertices: V,
+        indices: I,
+        keep_cpu_data: bool,
+    ) -> (Self, Box<GpuFuture + Send + Sync>)
+    where
+        V: ExactSizeIterator<Item = Vertex>,
+        I: IntoIterator,
+        I::IntoIter: ExactSizeIterator,
+        I::Item: IndexFormat,
+    {
+        let mut positions = Vec::with_capacity(vertices.len());
         let (vertices, vertices_future) = ImmutableBuffer::from_iter(
-            vertices.into_iter().map(Vertex::new),
+            vertices.inspect(|vertex| positions.push(vertex.position)),
             BufferUsage::vertex_buffer(),
             Arc::clone(ctx.transfer_queue()),
         ).unwrap_or_else(throw);
+        let indices = indices.into_iter();
+        let mut cpu_indices = Vec::with_capacity(if keep_cpu_data { indices.len() } else { 0 });
         let (indices, indices_future) = ImmutableBuffer::from_iter(
-            indices.into_iter(),
+            indices.inspect(|&index| {
+                if keep_cpu_data {
+                    cpu_indices.push(index.to_u32());
+                }
+            }),
             BufferUsage::index_buffer(),
             Arc::clone(ctx.transfer_queue()),
         ).unwrap_or_else(throw);
 
         let future = vertices_future.join(indices_future);
-        (Mesh { vertices, indices }, Box::new(future))
+        let mesh = Mesh {
+            vertices,
+            indices: IndexFormat::wrap(indices),
+            aabb: Aabb::from_points(positions.iter().cloned()),
+            sphere: Sphere::from_points(&positions),
+            cpu_data: if keep_cpu_data {
+                Some(CpuData {
+                    positions: positions.into(),
+                    indices: cpu_indices.into(),
+                })
+            } else {
+                None
+            },
+            asset: None,
+        };
+        (mesh, Box::new(future))
+    }
+
+    /// The bounding box of the mesh's vertices, in model space.
+    pub fn aabb(&self) -> &Aabb {
+        &self.aabb
+    }
+
+    /// A bounding sphere containing the mesh's vertices, in model space.
+    pub fn bounding_sphere(&self) -> &Sphere {
+        &self.sphere
+    }
+
+    /// The vertex positions of the mesh, in model space, if the mesh was
+    /// built with `MeshBuilder::keep_cpu_data`.
+    pub fn positions(&self) -> Option<&[Vector]> {
+        self.cpu_data.as_ref().map(|data| &*data.positions)
+    }
+
+    /// The vertex indices of the mesh's triangles, if the mesh was built with
+    /// `MeshBuilder::keep_cpu_data`.
+    pub fn indices(&self) -> Option<&[u32]> {
+        self.cpu_data.as_ref().map(|data| &*data.indices)
+    }
+
+    /// The upload tracking this mesh, if it was passed to `Uploads`.
+    pub fn asset(&self) -> Option<AssetId> {
+        self.asset
+    }
+
+    pub(crate) fn set_asset(&mut self, asset: AssetId) {
+        self.asset = Some(asset);
+    }
+
+    /// Identifies the GPU buffers backing this mesh, so that clones of the
+    /// same mesh can be batched together.
+    pub(in d3) fn key(&self) -> (usize, usize) {
+        let indices = match self.indices {
+            Indices::U16(ref buf) => &**buf as *const ImmutableBuffer<[u16]> as *const u8,
+            Indices::U32(ref buf) => &**buf as *const ImmutableBuffer<[u32]> as *const u8,
+        };
+        (
+            &*self.vertices as *const ImmutableBuffer<[Vertex]> as *const u8 as usize,
+            indices as usize,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use d3::MeshBuilder;
+    use Context;
+
+    #[test]
+    fn cpu_data() {
+        let ctx = Context::for_tests();
+        let (mesh, _) = MeshBuilder::cube(1.0).build(&ctx);
+        assert!(mesh.positions().is_none());
+        assert!(mesh.indices().is_none());
+
+        let (mesh, _) = MeshBuilder::cube(1.0).keep_cpu_data().build(&ctx);
+        assert_eq!(mesh.positions().map(<[_]>::len), Some(24));
+        assert_eq!(mesh.indices().map(<[_]>::len), Some(36));
     }
 }
@@ -1,25 +1,61 @@
+mod bounds;
+mod builder;
+mod camera;
+mod debug;
 mod geom;
+mod instance;
+mod load;
+mod matrix;
 mod mesh;
+mod ray;
 
+pub use self::bounds::{Aabb, Sphere};
+pub use self::builder::MeshBuilder;
+pub use self::camera::{Camera, Frustum};
+pub use self::debug::{Debug, DebugLine};
 pub use self::geom::Vector;
+pub use self::instance::InstanceData;
+pub use self::load::{load_gltf, load_obj, LoadError};
+pub use self::matrix::Matrix;
 pub use self::mesh::Mesh;
+pub use self::ray::{PickMode, Ray, RayHit};
 
+use std::collections::HashMap;
+use std::mem;
 use std::sync::Arc;
 
 use failure::Fallible;
-use utils::throw;
-use vulkano::buffer::CpuBufferPool;
+use vulkano::buffer::{CpuBufferPool, TypedBufferAccess};
 use vulkano::command_buffer::{AutoCommandBufferBuilder, DynamicState};
 use vulkano::descriptor::descriptor_set::FixedSizeDescriptorSetsPool;
 use vulkano::descriptor::PipelineLayoutAbstract;
 use vulkano::device::Device;
 use vulkano::framebuffer::{RenderPassAbstract, Subpass};
-use vulkano::pipeline::vertex::SingleBufferDefinition;
+use vulkano::pipeline::shader::ShaderModule;
+use vulkano::pipeline::vertex::OneVertexOneInstanceDefinition;
 use vulkano::pipeline::GraphicsPipeline;
 
+use d3::debug::DebugPipeline;
+use d3::instance::IDENTITY;
+use d3::mesh::Indices;
+#[cfg(all(feature = "hot-reload", debug_assertions))]
+use reload::{self, ShaderType};
+use {Color, RenderStats, Uploads};
+
+/// The shader sources, relative to the `shader` directory.
+#[cfg(all(feature = "hot-reload", debug_assertions))]
+pub(crate) const VERT_PATH: &str = "d3/vert.glsl";
+#[cfg(all(feature = "hot-reload", debug_assertions))]
+pub(crate) const FRAG_PATH: &str = "d3/frag.glsl";
+/// The sources compiled into the crate.
+#[cfg(all(feature = "hot-reload", debug_assertions))]
+const VERT_SOURCE: &str = include_str!("../../shader/d3/vert.glsl");
+#[cfg(all(feature = "hot-reload", debug_assertions))]
+const FRAG_SOURCE: &str = include_str!("../../shader/d3/frag.glsl");
+
 type Pipeline = Arc<
     GraphicsPipeline<
-        SingleBufferDefinition<Vertex>,
+        OneVertexOneInstanceDefinition<Vertex, InstanceData>,
         Box<PipelineLayoutAbstract + Send + Sync>,
         Arc<RenderPassAbstract + Send + Sync>,
     >,
@@ -27,100 +63,275 @@ type Pipeline = Arc<
 
 pub struct Renderer {
     ubuf: CpuBufferPool<vs::ty::Data>,
+    ibuf: CpuBufferPool<InstanceData>,
     pool: FixedSizeDescriptorSetsPool<Pipeline>,
     pipe: Pipeline,
-    queued: Vec<Mesh>,
+    debug: DebugPipeline,
+    queued: Vec<Batch>,
+    batches: HashMap<(usize, usize), usize>,
+    camera: Option<Camera>,
+    culled: usize,
+    skipped: usize,
+}
+
+struct Batch {
+    mesh: Mesh,
+    instances: Vec<InstanceData>,
 }
 
 impl Renderer {
     pub(crate) fn new(
         device: &Arc<Device>,
         subpass: Subpass<Arc<RenderPassAbstract + Send + Sync>>,
-    ) -> Self {
-        let vs = vs::Shader::load(Arc::clone(device)).unwrap_or_else(throw);
-        let fs = fs::Shader::load(Arc::clone(device)).unwrap_or_else(throw);
-
-        let pipe = Arc::new(
-            GraphicsPipeline::start()
-                .vertex_input(SingleBufferDefinition::new())
-                .vertex_shader(vs.main_entry_point(), ())
-                .triangle_list()
-                .viewports_dynamic_scissors_irrelevant(1)
-                .fragment_shader(fs.main_entry_point(), ())
-                .depth_stencil_simple_depth()
-                .render_pass(subpass)
-                .build(Arc::clone(device))
-                .unwrap_or_else(throw),
-        );
+    ) -> Fallible<Self> {
+        let vs = vs::Shader::load(Arc::clone(device))?;
+        let fs = fs::Shader::load(Arc::clone(device))?;
+        let pipe = create_pipeline(device, subpass.clone(), vs.module(), fs.module())?;
+        let debug = DebugPipeline::new(device, subpass)?;
 
         let ubuf = CpuBufferPool::uniform_buffer(Arc::clone(device));
+        let ibuf = CpuBufferPool::vertex_buffer(Arc::clone(device));
         let pool = FixedSizeDescriptorSetsPool::new(Arc::clone(&pipe), 0);
 
-        Renderer {
+        Ok(Renderer {
             pipe,
+            debug,
             ubuf,
+            ibuf,
             queued: Vec::new(),
+            batches: HashMap::new(),
+            camera: None,
+            culled: 0,
+            skipped: 0,
             pool,
-        }
+        })
+    }
+
+    /// Recompile the shaders from source and rebuild the pipeline. On failure
+    /// the old pipeline is kept.
+    #[cfg(all(feature = "hot-reload", debug_assertions))]
+    pub(crate) fn reload_shaders(
+        &mut self,
+        device: &Arc<Device>,
+        subpass: Subpass<Arc<RenderPassAbstract + Send + Sync>>,
+    ) -> Fallible<()> {
+        let vs = reload::compile(device, VERT_PATH, VERT_SOURCE, ShaderType::Vertex)?;
+        let fs = reload::compile(device, FRAG_PATH, FRAG_SOURCE, ShaderType::Fragment)?;
+        let pipe = create_pipeline(device, subpass, &vs, &fs)?;
+
+        self.pool = FixedSizeDescriptorSetsPool::new(Arc::clone(&pipe), 0);
+        self.pipe = pipe;
+        Ok(())
     }
 
     pub(crate) fn commit(
         &mut self,
         mut cmd: AutoCommandBufferBuilder,
         state: &DynamicState,
+        uploads: &Uploads,
+        dimensions: [u32; 2],
+        stats: &mut RenderStats,
     ) -> Fallible<AutoCommandBufferBuilder> {
+        let aspect = aspect_ratio(dimensions);
+        let (view, proj) = match self.camera {
+            Some(ref camera) => (camera.view(), camera.proj(aspect)),
+            None => (Matrix::identity(), Matrix::identity()),
+        };
+        let frustum = self.camera.map(|camera| camera.frustum(aspect));
+
         let ubuf = self.ubuf.next(vs::ty::Data {
-            world: [
-                [1.0, 0.0, 0.0, 0.0],
-                [0.0, 1.0, 0.0, 0.0],
-                [0.0, 0.0, 1.0, 0.0],
-                [0.0, 0.0, 0.0, 1.0],
-            ],
-            view: [
-                [1.0, 0.0, 0.0, 0.0],
-                [0.0, 1.0, 0.0, 0.0],
-                [0.0, 0.0, 1.0, 0.0],
-                [0.0, 0.0, 0.0, 1.0],
-            ],
-            proj: [
-                [1.0, 0.0, 0.0, 0.0],
-                [0.0, 1.0, 0.0, 0.0],
-                [0.0, 0.0, 1.0, 0.0],
-                [0.0, 0.0, 0.0, 1.0],
-            ],
+            world: IDENTITY,
+            view: view.into(),
+            proj: proj.into(),
         })?;
         let set = Arc::new(self.pool.next().add_buffer(ubuf)?.build()?);
-        for mesh in self.queued.drain(..) {
-            cmd = cmd.draw_indexed(
-                Arc::clone(&self.pipe),
-                state,
-                mesh.vertices,
-                mesh.indices,
-                Arc::clone(&set),
-                (),
-            )?;
+        stats.uploaded_bytes += mem::size_of::<vs::ty::Data>();
+        self.batches.clear();
+        self.culled = 0;
+        self.skipped = 0;
+        for mut batch in self.queued.drain(..) {
+            if let Some(asset) = batch.mesh.asset() {
+                if !uploads.is_ready(asset) {
+                    self.skipped += batch.instances.len();
+                    continue;
+                }
+            }
+
+            if let Some(ref frustum) = frustum {
+                let sphere = *batch.mesh.bounding_sphere();
+                let len = batch.instances.len();
+                batch.instances.retain(|instance| {
+                    frustum.intersects_sphere(&sphere.transform(&instance.transform.into()))
+                });
+                self.culled += len - batch.instances.len();
+                if batch.instances.is_empty() {
+                    continue;
+                }
+            }
+
+            stats.draw_calls += 1;
+            stats.meshes += batch.instances.len();
+            stats.vertices += batch.mesh.vertices.len() * batch.instances.len();
+            stats.indices += batch.mesh.indices.len() * batch.instances.len();
+            stats.uploaded_bytes += batch.instances.len() * mem::size_of::<InstanceData>();
+
+            let instances = self.ibuf.chunk(batch.instances)?;
+            let vertices = (batch.mesh.vertices, instances);
+            cmd = match batch.mesh.indices {
+                Indices::U16(indices) => cmd.draw_indexed(
+                    Arc::clone(&self.pipe),
+                    state,
+                    vertices,
+                    indices,
+                    Arc::clone(&set),
+                    (),
+                )?,
+                Indices::U32(indices) => cmd.draw_indexed(
+                    Arc::clone(&self.pipe),
+                    state,
+                    vertices,
+                    indices,
+                    Arc::clone(&set),
+                    (),
+                )?,
+            };
         }
         Ok(cmd)
     }
 
+    /// Draw the lines queued on `debug` with the current camera.
+    pub(crate) fn commit_debug(
+        &mut self,
+        cmd: AutoCommandBufferBuilder,
+        state: &DynamicState,
+        debug: &mut Debug,
+        dimensions: [u32; 2],
+        stats: &mut RenderStats,
+    ) -> Fallible<AutoCommandBufferBuilder> {
+        let view_proj = match self.camera {
+            Some(ref camera) => camera.proj(aspect_ratio(dimensions)) * camera.view(),
+            None => Matrix::identity(),
+        };
+        self.debug.commit(cmd, state, debug, view_proj, stats)
+    }
+
+    /// Set the camera used to view meshes. Without a camera, mesh vertices are
+    /// used directly as clip space coordinates and nothing is culled.
+    pub fn set_camera(&mut self, camera: Option<Camera>) {
+        self.camera = camera;
+    }
+
+    pub fn camera(&self) -> Option<&Camera> {
+        self.camera.as_ref()
+    }
+
+    /// The number of mesh instances skipped in the last frame because they
+    /// were outside the camera's view frustum.
+    pub fn culled(&self) -> usize {
+        self.culled
+    }
+
+    /// The number of mesh instances skipped in the last frame because their
+    /// mesh was still being uploaded.
+    pub fn skipped(&self) -> usize {
+        self.skipped
+    }
+
+    /// The number of draw calls queued so far this frame. Instances of the same
+    /// mesh share a draw call.
+    pub fn batches(&self) -> usize {
+        self.queued.len()
+    }
+
+    /// Drop everything queued for a frame that could not be drawn.
+    pub(crate) fn discard(&mut self) {
+        self.queued.clear();
+    }
+
+    /// Draw a single instance of a mesh with no transform.
     pub fn draw_mesh(&mut self, mesh: Mesh) {
-        self.queued.push(mesh);
+        self.draw_mesh_instanced(mesh, &[InstanceData::identity(Color::RED)]);
+    }
+
+    /// Draw several instances of a mesh. Instances of the same mesh are drawn
+    /// together in a single draw call, including across separate calls to this
+    /// method in the same frame.
+    pub fn draw_mesh_instanced(&mut self, mesh: Mesh, instances: &[InstanceData]) {
+        if instances.is_empty() {
+            return;
+        }
+
+        let queued = &mut self.queued;
+        let idx = *self.batches.entry(mesh.key()).or_insert_with(|| {
+            queued.push(Batch {
+                mesh,
+                instances: Vec::new(),
+            });
+            queued.len() - 1
+        });
+        queued[idx].instances.extend_from_slice(instances);
+    }
+}
+
+/// The aspect ratio of a frame. An empty frame, such as a minimized window, is
+/// treated as square so that the projection stays finite.
+fn aspect_ratio([width, height]: [u32; 2]) -> f32 {
+    if width == 0 || height == 0 {
+        1.0
+    } else {
+        width as f32 / height as f32
     }
 }
 
+fn create_pipeline(
+    device: &Arc<Device>,
+    subpass: Subpass<Arc<RenderPassAbstract + Send + Sync>>,
+    vs: &ShaderModule,
+    fs: &ShaderModule,
+) -> Fallible<Pipeline> {
+    // Both modules are compiled from the sources of `vs` and `fs`.
+    let (vs, fs) = unsafe {
+        (
+            main_entry_point!(vs, vs, vertex, Vertex),
+            main_entry_point!(fs, fs, fragment, Fragment),
+        )
+    };
+
+    Ok(Arc::new(
+        GraphicsPipeline::start()
+            .vertex_input(OneVertexOneInstanceDefinition::new())
+            .vertex_shader(vs, ())
+            .triangle_list()
+            .viewports_dynamic_scissors_irrelevant(1)
+            .fragment_shader(fs, ())
+            .depth_stencil_simple_depth()
+            .render_pass(subpass)
+            .build(Arc::clone(device))?,
+    ))
+}
+
 #[derive(Copy, Clone, Debug)]
 struct Vertex {
     position: Vector,
     normal: Vector,
+    uv: [f32; 2],
 }
 
 impl Vertex {
     fn new((position, normal): (Vector, Vector)) -> Self {
-        Vertex { position, normal }
+        Vertex::with_uv((position, normal, [0.0, 0.0]))
+    }
+
+    fn with_uv((position, normal, uv): (Vector, Vector, [f32; 2])) -> Self {
+        Vertex {
+            position,
+            normal,
+            uv,
+        }
     }
 }
 
-impl_vertex!(Vertex, position, normal);
+impl_vertex!(Vertex, position, normal, uv);
 
 #[allow(unused)]
 mod vs {
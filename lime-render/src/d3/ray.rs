@@ -0,0 +1,215 @@
+use std::f32;
+
+use d3::{Aabb, Matrix, Mesh, Sphere, Vector};
+
+/// A half-line in world space, used for picking.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Ray {
+    pub origin: Vector,
+    /// A unit vector.
+    pub dir: Vector,
+}
+
+/// Where a ray hit something.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct RayHit {
+    /// The distance along the ray.
+    pub distance: f32,
+    pub point: Vector,
+    /// The index of the triangle that was hit, if triangles were tested.
+    pub triangle: Option<usize>,
+}
+
+/// What a ray is tested against when picking meshes.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum PickMode {
+    /// Only the mesh's bounding box. This is cheap, but hits may be reported
+    /// in the empty space around a mesh.
+    Bounds,
+    /// Every triangle of meshes whose bounds are hit. Meshes built without
+    /// `MeshBuilder::keep_cpu_data` are only tested against their bounds.
+    Triangles,
+}
+
+impl Ray {
+    /// Create a ray, normalizing its direction.
+    pub fn new(origin: Vector, dir: Vector) -> Self {
+        Ray {
+            origin,
+            dir: dir.normalize(),
+        }
+    }
+
+    /// The point at `distance` along the ray.
+    pub fn at(&self, distance: f32) -> Vector {
+        self.origin + self.dir * distance
+    }
+
+    /// The distance at which the ray enters the box, or zero if it starts
+    /// inside it.
+    pub fn intersect_aabb(&self, aabb: &Aabb) -> Option<f32> {
+        if aabb.is_empty() {
+            return None;
+        }
+
+        let origin = [self.origin.0, self.origin.1, self.origin.2];
+        let dir = [self.dir.0, self.dir.1, self.dir.2];
+        let min = [aabb.min.0, aabb.min.1, aabb.min.2];
+        let max = [aabb.max.0, aabb.max.1, aabb.max.2];
+
+        let (mut near, mut far) = (0.0f32, f32::INFINITY);
+        for axis in 0..3 {
+            if dir[axis] == 0.0 {
+                if origin[axis] < min[axis] || max[axis] < origin[axis] {
+                    return None;
+                }
+                continue;
+            }
+            let t0 = (min[axis] - origin[axis]) / dir[axis];
+            let t1 = (max[axis] - origin[axis]) / dir[axis];
+            near = near.max(t0.min(t1));
+            far = far.min(t0.max(t1));
+            if near > far {
+                return None;
+            }
+        }
+        Some(near)
+    }
+
+    /// The distance at which the ray enters the sphere, or zero if it starts
+    /// inside it.
+    pub fn intersect_sphere(&self, sphere: &Sphere) -> Option<f32> {
+        let offset = self.origin - sphere.center;
+        let b = offset.dot(self.dir);
+        let c = offset.dot(offset) - sphere.radius * sphere.radius;
+        if c <= 0.0 {
+            return Some(0.0);
+        }
+        let discriminant = b * b - c;
+        if b > 0.0 || discriminant < 0.0 {
+            return None;
+        }
+        Some(-b - discriminant.sqrt())
+    }
+
+    /// The distance at which the ray hits a triangle from either side.
+    pub fn intersect_triangle(&self, triangle: [Vector; 3]) -> Option<f32> {
+        triangle_distance(self.origin, self.dir, triangle)
+    }
+
+    /// The closest triangle hit by the ray, where `indices` lists the vertices
+    /// of each triangle and `transform` takes `positions` to world space.
+    ///
+    /// The ray is moved into model space rather than moving every vertex into
+    /// world space. Returns `None` if `transform` cannot be inverted.
+    pub fn intersect_triangles(
+        &self,
+        positions: &[Vector],
+        indices: &[u32],
+        transform: &Matrix,
+    ) -> Option<RayHit> {
+        let inverse = transform.inverse()?;
+        // The direction is not normalized, so that distances along it are the
+        // same as distances along the ray in world space.
+        let origin = inverse.transform_point(self.origin);
+        let dir = inverse.transform_vector(self.dir);
+
+        let mut closest: Option<RayHit> = None;
+        for (triangle, tri) in indices.chunks(3).enumerate() {
+            if tri.len() < 3 {
+                break;
+            }
+            let vertices = [
+                positions[tri[0] as usize],
+                positions[tri[1] as usize],
+                positions[tri[2] as usize],
+            ];
+            if let Some(distance) = triangle_distance(origin, dir, vertices) {
+                if closest.map(|hit| distance < hit.distance).unwrap_or(true) {
+                    closest = Some(RayHit {
+                        distance,
+                        point: self.at(distance),
+                        triangle: Some(triangle),
+                    });
+                }
+            }
+        }
+        closest
+    }
+
+    /// Test the ray against a mesh drawn with `transform`.
+    pub fn intersect_mesh(
+        &self,
+        mesh: &Mesh,
+        transform: &Matrix,
+        mode: PickMode,
+    ) -> Option<RayHit> {
+        self.intersect_sphere(&mesh.bounding_sphere().transform(transform))?;
+        match mode {
+            PickMode::Bounds => {
+                let distance = self.intersect_aabb(&mesh.aabb().transform(transform))?;
+                Some(RayHit {
+                    distance,
+                    point: self.at(distance),
+                    triangle: None,
+                })
+            }
+            PickMode::Triangles => match (mesh.positions(), mesh.indices()) {
+                (Some(positions), Some(indices)) => {
+                    self.intersect_triangles(positions, indices, transform)
+                }
+                _ => self.intersect_mesh(mesh, transform, PickMode::Bounds),
+            },
+        }
+    }
+
+    /// Find the closest of `meshes` hit by the ray. Each mesh is paired with
+    /// its transform and a key identifying it, which is returned with the hit.
+    pub fn pick<'a, K, I>(&self, meshes: I, mode: PickMode) -> Option<(K, RayHit)>
+    where
+        I: IntoIterator<Item = (K, &'a Mesh, Matrix)>,
+    {
+        let mut closest: Option<(K, RayHit)> = None;
+        for (key, mesh, transform) in meshes {
+            if let Some(hit) = self.intersect_mesh(mesh, &transform, mode) {
+                let closer = match closest {
+                    Some((_, ref best)) => hit.distance < best.distance,
+                    None => true,
+                };
+                if closer {
+                    closest = Some((key, hit));
+                }
+            }
+        }
+        closest
+    }
+}
+
+/// The distance along `dir` at which a ray hits a triangle from either side,
+/// in multiples of the length of `dir`.
+fn triangle_distance(origin: Vector, dir: Vector, [a, b, c]: [Vector; 3]) -> Option<f32> {
+    // Möller–Trumbore.
+    let ab = b - a;
+    let ac = c - a;
+    let p = dir.cross(ac);
+    let det = ab.dot(p);
+    if det.abs() < f32::EPSILON {
+        return None;
+    }
+
+    let inv_det = 1.0 / det;
+    let t = origin - a;
+    let q = t.cross(ab);
+    let u = t.dot(p) * inv_det;
+    let v = dir.dot(q) * inv_det;
+    if u < 0.0 || v < 0.0 || 1.0 < u + v {
+        return None;
+    }
+
+    let distance = ac.dot(q) * inv_det;
+    if distance >= 0.0 {
+        Some(distance)
+    } else {
+        None
+    }
+}
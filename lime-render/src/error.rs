@@ -0,0 +1,131 @@
+use std::error::Error;
+use std::fmt;
+
+use failure;
+use vulkano::device::DeviceCreationError;
+use vulkano::memory::DeviceMemoryAllocError;
+use vulkano::swapchain::{AcquireError, CapabilitiesError, SwapchainCreationError};
+use vulkano::sync::FlushError;
+use vulkano::OomError;
+
+/// An error encountered while rendering.
+///
+/// Errors are published on an `EventChannel<RenderError>` resource. The render
+/// system recovers from each kind of error by itself where possible; the events
+/// are for applications that want to report them or react to device loss.
+#[derive(Clone, Debug, PartialEq)]
+pub enum RenderError {
+    /// The swapchain no longer matches the surface, usually because the window
+    /// was resized. The swapchain is recreated before the next frame.
+    OutOfDate,
+    /// The surface was destroyed. This cannot be recovered from, and no more
+    /// frames will be rendered.
+    SurfaceLost,
+    /// The device was lost. The `Context`, target and renderers are rebuilt
+    /// before the next frame, so meshes and other resources created on the old
+    /// device must be created again.
+    DeviceLost,
+    /// Host or device memory was exhausted. The frame is skipped.
+    OutOfMemory,
+    /// Any other error. The swapchain is recreated before the next frame.
+    Other(String),
+}
+
+impl RenderError {
+    fn classify(err: &failure::Error) -> Option<Self> {
+        if let Some(err) = err.downcast_ref::<AcquireError>() {
+            return match err {
+                AcquireError::OutOfDate => Some(RenderError::OutOfDate),
+                AcquireError::SurfaceLost => Some(RenderError::SurfaceLost),
+                AcquireError::DeviceLost => Some(RenderError::DeviceLost),
+                AcquireError::OomError(_) => Some(RenderError::OutOfMemory),
+                _ => None,
+            };
+        }
+        if let Some(err) = err.downcast_ref::<FlushError>() {
+            return match err {
+                FlushError::OutOfDate => Some(RenderError::OutOfDate),
+                FlushError::SurfaceLost => Some(RenderError::SurfaceLost),
+                FlushError::DeviceLost => Some(RenderError::DeviceLost),
+                FlushError::OomError(_) => Some(RenderError::OutOfMemory),
+                _ => None,
+            };
+        }
+        if let Some(err) = err.downcast_ref::<SwapchainCreationError>() {
+            return match err {
+                SwapchainCreationError::SurfaceLost => Some(RenderError::SurfaceLost),
+                SwapchainCreationError::DeviceLost => Some(RenderError::DeviceLost),
+                SwapchainCreationError::OomError(_) => Some(RenderError::OutOfMemory),
+                _ => None,
+            };
+        }
+        if let Some(err) = err.downcast_ref::<CapabilitiesError>() {
+            return match err {
+                CapabilitiesError::SurfaceLost => Some(RenderError::SurfaceLost),
+                CapabilitiesError::OomError(_) => Some(RenderError::OutOfMemory),
+            };
+        }
+        if let Some(err) = err.downcast_ref::<DeviceCreationError>() {
+            return match err {
+                DeviceCreationError::DeviceLost => Some(RenderError::DeviceLost),
+                DeviceCreationError::OutOfHostMemory
+                | DeviceCreationError::OutOfDeviceMemory => Some(RenderError::OutOfMemory),
+                _ => None,
+            };
+        }
+        if let Some(err) = err.downcast_ref::<DeviceMemoryAllocError>() {
+            return match err {
+                DeviceMemoryAllocError::OomError(_) => Some(RenderError::OutOfMemory),
+                _ => None,
+            };
+        }
+        if err.downcast_ref::<OomError>().is_some() {
+            return Some(RenderError::OutOfMemory);
+        }
+        None
+    }
+}
+
+impl From<failure::Error> for RenderError {
+    fn from(err: failure::Error) -> Self {
+        RenderError::classify(&err).unwrap_or_else(|| RenderError::Other(err.to_string()))
+    }
+}
+
+impl fmt::Display for RenderError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RenderError::OutOfDate => write!(f, "swapchain is out of date"),
+            RenderError::SurfaceLost => write!(f, "surface was lost"),
+            RenderError::DeviceLost => write!(f, "device was lost"),
+            RenderError::OutOfMemory => write!(f, "out of memory"),
+            RenderError::Other(msg) => msg.fmt(f),
+        }
+    }
+}
+
+impl Error for RenderError {}
+
+#[cfg(test)]
+mod tests {
+    use failure::err_msg;
+
+    use super::*;
+
+    #[test]
+    fn classify() {
+        let classify = |err: failure::Error| RenderError::from(err);
+        assert_eq!(classify(AcquireError::OutOfDate.into()), RenderError::OutOfDate);
+        assert_eq!(classify(FlushError::DeviceLost.into()), RenderError::DeviceLost);
+        assert_eq!(classify(CapabilitiesError::SurfaceLost.into()), RenderError::SurfaceLost);
+        assert_eq!(
+            classify(SwapchainCreationError::OomError(OomError::OutOfDeviceMemory).into()),
+            RenderError::OutOfMemory
+        );
+        assert_eq!(classify(OomError::OutOfHostMemory.into()), RenderError::OutOfMemory);
+        assert_eq!(
+            classify(err_msg("something else")),
+            RenderError::Other("something else".to_owned())
+        );
+    }
+}
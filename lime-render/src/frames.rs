@@ -0,0 +1,140 @@
+//! Frames in flight.
+//!
+//! The render system cycles through a fixed number of frame slots. Each slot
+//! owns the fence of the last frame rendered with it, and before a slot is
+//! reused the CPU waits for that fence. This lets the CPU record a frame while
+//! the GPU is still executing up to `FramesInFlight` earlier ones, without ever
+//! getting further ahead.
+//!
+//! Per-frame resources can be indexed by `Frame::slot`: anything owned by a
+//! slot is no longer in use by the GPU when that slot is drawn again. Of the
+//! built-in resources, only the d2 vertex and index buffers are owned by slots.
+//! The uniforms and instance data of d3, post-processing and debug lines come
+//! from `CpuBufferPool`s, which are safe to share since each chunk is
+//! reclaimed once the frame using it has finished. Command buffers are
+//! allocated from vulkano's standard command pool, since
+//! `AutoCommandBufferBuilder` cannot be given a pool per slot.
+
+use std::sync::Arc;
+
+use failure::Fallible;
+use vulkano::sync::{FenceSignalFuture, GpuFuture};
+
+pub(crate) type Fence = Arc<FenceSignalFuture<Box<GpuFuture + Send + Sync>>>;
+
+/// The number of frames the GPU may be rendering at once.
+///
+/// If this resource exists when `init` is called it configures the render
+/// system, otherwise two frames are used. It is clamped to at least one.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct FramesInFlight(pub usize);
+
+impl Default for FramesInFlight {
+    fn default() -> Self {
+        FramesInFlight(2)
+    }
+}
+
+pub(crate) struct FrameSlots {
+    fences: Vec<Option<Fence>>,
+    current: usize,
+}
+
+impl FrameSlots {
+    pub(crate) fn new(FramesInFlight(count): FramesInFlight) -> Self {
+        FrameSlots {
+            fences: vec![None; count.max(1)],
+            current: 0,
+        }
+    }
+
+    pub(crate) fn count(&self) -> usize {
+        self.fences.len()
+    }
+
+    /// The slot the next frame will be rendered with.
+    pub(crate) fn current(&self) -> usize {
+        self.current
+    }
+
+    /// Release finished frames, and wait until the GPU has finished the frame
+    /// last rendered with the current slot.
+    pub(crate) fn wait(&mut self) -> Fallible<()> {
+        for fence in &mut self.fences {
+            if let Some(ref mut fence) = *fence {
+                fence.cleanup_finished();
+            }
+        }
+
+        if let Some(fence) = self.fences[self.current].take() {
+            fence.wait(None)?;
+        }
+        Ok(())
+    }
+
+    /// Record the fence of the frame rendered with the current slot and move on
+    /// to the next slot.
+    pub(crate) fn finish(&mut self, fence: Fence) {
+        self.fences[self.current] = Some(fence);
+        self.current = (self.current + 1) % self.fences.len();
+    }
+
+    /// Forget every frame in flight, for example because the device was lost.
+    pub(crate) fn clear(&mut self) {
+        for fence in &mut self.fences {
+            *fence = None;
+        }
+        self.current = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use d3::MeshBuilder;
+    use Context;
+
+    fn fence(ctx: &Context) -> Fence {
+        Arc::new(MeshBuilder::cube(1.0).build(ctx).1.then_signal_fence_and_flush().unwrap())
+    }
+
+    #[test]
+    fn wrap_around() {
+        let ctx = Context::for_tests();
+        let mut slots = FrameSlots::new(FramesInFlight(2));
+        assert_eq!(slots.count(), 2);
+        for &slot in &[0, 1, 0, 1, 0] {
+            assert_eq!(slots.current(), slot);
+            slots.wait().unwrap();
+            // Waiting releases the fence of the frame last drawn in the slot.
+            assert!(slots.fences[slot].is_none());
+            slots.finish(fence(&ctx));
+            assert!(slots.fences[slot].is_some());
+        }
+    }
+
+    #[test]
+    fn at_least_one_slot() {
+        let ctx = Context::for_tests();
+        let mut slots = FrameSlots::new(FramesInFlight(0));
+        assert_eq!(slots.count(), 1);
+        slots.wait().unwrap();
+        slots.finish(fence(&ctx));
+        assert_eq!(slots.current(), 0);
+        slots.wait().unwrap();
+    }
+
+    #[test]
+    fn clear() {
+        let ctx = Context::for_tests();
+        let mut slots = FrameSlots::new(FramesInFlight(3));
+        slots.finish(fence(&ctx));
+        slots.finish(fence(&ctx));
+        assert_eq!(slots.current(), 2);
+
+        slots.clear();
+        assert_eq!(slots.current(), 0);
+        assert!(slots.fences.iter().all(Option::is_none));
+        slots.wait().unwrap();
+    }
+}
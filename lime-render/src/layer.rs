@@ -0,0 +1,257 @@
+//! Custom render layers.
+//!
+//! A layer records its own draw commands into one of the three stages of the
+//! frame, with pipelines built for that stage's subpass. Layers with a negative
+//! order are drawn before the built-in renderer for their stage, and layers with
+//! a non-negative order after it. Layers with the same stage and order are
+//! drawn in the order they were added.
+//!
+//! Systems can reach a layer through the `Layers` resource to update the data
+//! it draws. Such systems should be passed to `init` so that they run before
+//! the frame is rendered.
+
+use std::any::Any;
+use std::sync::Arc;
+
+use failure::{err_msg, Fallible};
+use vulkano::command_buffer::{AutoCommandBufferBuilder, DynamicState};
+use vulkano::device::Device;
+use vulkano::framebuffer::{RenderPassAbstract, Subpass};
+
+use Timings;
+
+/// The part of the frame a layer is drawn in.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Stage {
+    /// The 3D scene, drawn with a depth buffer into the HDR scene image.
+    Scene,
+    /// Post-processing, drawn into the target with the scene image available
+    /// as an input attachment. This stage only exists if post-processing is
+    /// enabled.
+    Post,
+    /// The 2D overlay, drawn into the target on top of everything else.
+    Overlay,
+}
+
+impl Stage {
+    /// The index of the subpass for this stage, or `None` if the stage does not
+    /// exist.
+    pub(crate) fn subpass(self, post: bool) -> Option<u32> {
+        match (self, post) {
+            (Stage::Scene, _) => Some(0),
+            (Stage::Post, true) => Some(1),
+            (Stage::Post, false) => None,
+            (Stage::Overlay, true) => Some(2),
+            (Stage::Overlay, false) => Some(1),
+        }
+    }
+}
+
+/// Information about the frame being rendered.
+pub struct Frame<'a> {
+    pub state: &'a DynamicState,
+    /// The size of the target in pixels.
+    pub dimensions: [u32; 2],
+    /// The size of the target in logical pixels, as used by d2.
+    pub logical_size: [f32; 2],
+    /// The frame-in-flight slot, from 0 up to `FramesInFlight`. Resources
+    /// owned by a slot are no longer in use by the GPU when it is drawn again.
+    pub slot: usize,
+}
+
+pub trait Layer: Send + Sync + 'static {
+    /// The name used to report timings for this layer.
+    fn name(&self) -> &str {
+        "layer"
+    }
+
+    /// Create pipelines for a subpass. This is called when the layer is added,
+    /// and again if the device is lost.
+    fn build(
+        &mut self,
+        device: &Arc<Device>,
+        subpass: Subpass<Arc<RenderPassAbstract + Send + Sync>>,
+    ) -> Fallible<()>;
+
+    /// Record draw commands for a frame.
+    fn draw(
+        &mut self,
+        cmd: AutoCommandBufferBuilder,
+        frame: &Frame,
+    ) -> Fallible<AutoCommandBufferBuilder>;
+}
+
+trait AnyLayer: Layer {
+    fn as_any_mut(&mut self) -> &mut Any;
+}
+
+impl<L: Layer> AnyLayer for L {
+    fn as_any_mut(&mut self) -> &mut Any {
+        self
+    }
+}
+
+/// Identifies a layer added to `Layers`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct LayerId(u64);
+
+struct Entry {
+    id: LayerId,
+    stage: Stage,
+    order: i32,
+    layer: Box<AnyLayer>,
+}
+
+pub struct Layers {
+    device: Arc<Device>,
+    render_pass: Arc<RenderPassAbstract + Send + Sync>,
+    post: bool,
+    entries: Vec<Entry>,
+    next: u64,
+}
+
+impl Layers {
+    pub(crate) fn new(
+        device: &Arc<Device>,
+        render_pass: &Arc<RenderPassAbstract + Send + Sync>,
+        post: bool,
+    ) -> Self {
+        Layers {
+            device: Arc::clone(device),
+            render_pass: Arc::clone(render_pass),
+            post,
+            entries: Vec::new(),
+            next: 0,
+        }
+    }
+
+    /// Build a layer and add it to the frame. This fails for `Stage::Post` if
+    /// post-processing is disabled.
+    pub fn add<L: Layer>(&mut self, stage: Stage, order: i32, mut layer: L) -> Fallible<LayerId> {
+        let subpass = self
+            .subpass(stage)
+            .ok_or_else(|| err_msg("post-processing is disabled"))?;
+        layer.build(&self.device, subpass)?;
+
+        let id = LayerId(self.next);
+        self.next += 1;
+
+        let idx = insert_position(
+            self.entries.iter().map(|entry| (entry.stage, entry.order)),
+            (stage, order),
+        );
+        self.entries.insert(
+            idx,
+            Entry {
+                id,
+                stage,
+                order,
+                layer: Box::new(layer),
+            },
+        );
+        Ok(id)
+    }
+
+    /// Remove a layer, returning whether it existed.
+    pub fn remove(&mut self, id: LayerId) -> bool {
+        let len = self.entries.len();
+        self.entries.retain(|entry| entry.id != id);
+        self.entries.len() != len
+    }
+
+    /// Get a layer, if it exists and has type `L`.
+    pub fn get_mut<L: Layer>(&mut self, id: LayerId) -> Option<&mut L> {
+        self.entries
+            .iter_mut()
+            .find(|entry| entry.id == id)
+            .and_then(|entry| entry.layer.as_any_mut().downcast_mut())
+    }
+
+    /// Draw the layers in a stage that come before or after the built-in
+    /// renderer.
+    pub(crate) fn draw(
+        &mut self,
+        mut cmd: AutoCommandBufferBuilder,
+        stage: Stage,
+        before_builtin: bool,
+        frame: &Frame,
+        timings: &mut Timings,
+    ) -> Fallible<AutoCommandBufferBuilder> {
+        for entry in &mut self.entries {
+            if entry.stage == stage && (entry.order < 0) == before_builtin {
+                let start = timings.start();
+                cmd = entry.layer.draw(cmd, frame)?;
+                timings.record(entry.layer.name(), start);
+            }
+        }
+        Ok(cmd)
+    }
+
+    /// Rebuild every layer for a new device and render pass.
+    pub(crate) fn rebuild(
+        &mut self,
+        device: &Arc<Device>,
+        render_pass: &Arc<RenderPassAbstract + Send + Sync>,
+    ) -> Fallible<()> {
+        self.device = Arc::clone(device);
+        self.render_pass = Arc::clone(render_pass);
+        for idx in 0..self.entries.len() {
+            // Layers are only added to stages that exist.
+            let subpass = self.subpass(self.entries[idx].stage).unwrap();
+            self.entries[idx].layer.build(&self.device, subpass)?;
+        }
+        Ok(())
+    }
+
+    fn subpass(&self, stage: Stage) -> Option<Subpass<Arc<RenderPassAbstract + Send + Sync>>> {
+        let index = stage.subpass(self.post)?;
+        Some(Subpass::from(Arc::clone(&self.render_pass), index).unwrap())
+    }
+}
+
+/// The index at which to add a layer so that layers stay sorted by stage and
+/// order, after any existing layers with the same stage and order.
+fn insert_position<I>(mut keys: I, key: (Stage, i32)) -> usize
+where
+    I: ExactSizeIterator<Item = (Stage, i32)>,
+{
+    let len = keys.len();
+    keys.position(|other| other > key).unwrap_or(len)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn insert_position() {
+        let added = [
+            (Stage::Overlay, 0),
+            (Stage::Scene, 0),
+            (Stage::Scene, -1),
+            (Stage::Post, 5),
+            (Stage::Scene, 0),
+            (Stage::Overlay, -3),
+        ];
+
+        let mut entries: Vec<((Stage, i32), usize)> = Vec::new();
+        for (id, &key) in added.iter().enumerate() {
+            let idx = super::insert_position(entries.iter().map(|&(key, _)| key), key);
+            entries.insert(idx, (key, id));
+        }
+
+        let ids: Vec<usize> = entries.iter().map(|&(_, id)| id).collect();
+        assert_eq!(ids, [2, 1, 4, 3, 5, 0]);
+    }
+
+    #[test]
+    fn stage_subpass() {
+        assert_eq!(Stage::Scene.subpass(true), Some(0));
+        assert_eq!(Stage::Post.subpass(true), Some(1));
+        assert_eq!(Stage::Overlay.subpass(true), Some(2));
+
+        assert_eq!(Stage::Scene.subpass(false), Some(0));
+        assert_eq!(Stage::Post.subpass(false), None);
+        assert_eq!(Stage::Overlay.subpass(false), Some(1));
+    }
+}
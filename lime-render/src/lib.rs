@@ -1,12 +1,34 @@
+//! Renderer.
+//!
+//! # Features
+//!
+//! - `hot-reload` (default): in debug builds, watch the GLSL sources of the d2
+//!   and d3 shaders, and rebuild their pipelines when the sources change. A
+//!   shader that fails to compile is logged and the old pipeline is kept.
+//!   Release builds always use the shaders compiled into the crate.
+//! - `testing`: the `testing` module, for golden image tests and benchmarks.
+//!
+//! # Limitations
+//!
+//! Pipelines are built without a Vulkan pipeline cache, so there is no cache to
+//! persist between launches. vulkano's `GraphicsPipelineBuilder` cannot build a
+//! pipeline with a cache, and every built-in pipeline is made with it.
+
 #[macro_use]
 extern crate approx;
 extern crate failure;
+extern crate gltf;
+#[cfg(all(feature = "hot-reload", debug_assertions))]
+extern crate glsl_to_spirv;
+extern crate image;
 extern crate lime_utils as utils;
 #[macro_use]
 extern crate log;
 extern crate serde;
 extern crate shrev;
 extern crate specs;
+extern crate tobj;
+extern crate vk_sys as vk;
 #[macro_use]
 extern crate serde_derive;
 #[macro_use]
@@ -18,26 +40,55 @@ extern crate vulkano_glyph;
 extern crate vulkano_win;
 extern crate winit;
 
+#[macro_use]
+mod shader;
+
 pub mod d2;
 pub mod d3;
+pub mod post;
+#[cfg(feature = "testing")]
+pub mod testing;
 
+mod capture;
 mod color;
 mod context;
+mod error;
+mod frames;
+mod layer;
+#[cfg(all(feature = "hot-reload", debug_assertions))]
+mod reload;
+mod screenshot;
+mod stats;
 mod sys;
 mod target;
+mod texture;
+mod timestamps;
+mod timings;
+mod upload;
 
-pub use self::color::Color;
+pub use self::capture::{capture_frames, CaptureFormat, CaptureOptions};
+pub use self::color::{Color, ParseError};
 pub use self::context::Context;
+pub use self::error::RenderError;
+pub use self::frames::FramesInFlight;
+pub use self::layer::{Frame, Layer, LayerId, Layers, Stage};
+pub use self::screenshot::Screenshot;
+pub use self::stats::RenderStats;
 pub use self::target::{ImageTarget, SwapchainTarget, Target};
+pub use self::texture::Texture;
+pub use self::timings::{PassTiming, Timings};
+pub use self::upload::{AssetId, Uploads};
 
 use specs::{DispatcherBuilder, World};
 
+/// Add the render system and its resources. `deps` names the systems that
+/// draw with the renderers or layers, which must run before each frame is
+/// rendered.
 pub fn init<T: Target>(
     world: &mut World,
     dispatcher: &mut DispatcherBuilder,
     data: T::InitData,
-    d3: &str,
-    d2: &str,
-) {
-    sys::RenderSystem::<T>::add(world, dispatcher, data, d3, d2);
+    deps: &[&str],
+) -> failure::Fallible<()> {
+    sys::RenderSystem::<T>::add(world, dispatcher, data, deps)
 }
@@ -0,0 +1,110 @@
+use std::sync::Arc;
+
+use failure::Fallible;
+use vulkano::buffer::{BufferAccess, BufferUsage, CpuAccessibleBuffer};
+use vulkano::command_buffer::{AutoCommandBufferBuilder, DynamicState};
+use vulkano::descriptor::descriptor::DescriptorDesc;
+use vulkano::descriptor::descriptor_set::DescriptorSetsCollection;
+use vulkano::descriptor::pipeline_layout::PipelineLayoutDescNames;
+use vulkano::device::Device;
+use vulkano::framebuffer::{RenderPassAbstract, Subpass};
+use vulkano::pipeline::shader::{
+    GraphicsEntryPoint, ShaderInterfaceDef, ShaderInterfaceDefMatch, SpecializationConstants,
+};
+use vulkano::pipeline::{GraphicsPipeline, GraphicsPipelineAbstract};
+
+/// A pipeline that runs a fragment shader over every pixel of the target.
+///
+/// The vertex shader emits a single triangle covering the viewport and passes
+/// no outputs to the fragment shader, which should use `gl_FragCoord` or
+/// `subpassLoad` to find its inputs.
+pub struct FullscreenPipeline {
+    pipe: Arc<GraphicsPipelineAbstract + Send + Sync>,
+    vbuf: Arc<CpuAccessibleBuffer<[Vertex]>>,
+}
+
+impl FullscreenPipeline {
+    pub fn new<Fs, Fi, Fo, Fl>(
+        device: &Arc<Device>,
+        subpass: Subpass<Arc<RenderPassAbstract + Send + Sync>>,
+        fs: GraphicsEntryPoint<Fs, Fi, Fo, Fl>,
+        specialization: Fs,
+        blend: bool,
+    ) -> Fallible<Self>
+    where
+        Fs: SpecializationConstants,
+        Fi: ShaderInterfaceDefMatch<vs::MainOutput>,
+        Fo: ShaderInterfaceDef,
+        Fl: PipelineLayoutDescNames + Clone + Send + Sync + 'static,
+    {
+        let vs = vs::Shader::load(Arc::clone(device))?;
+
+        let builder = GraphicsPipeline::start()
+            .vertex_input_single_buffer::<Vertex>()
+            .vertex_shader(vs.main_entry_point(), ())
+            .triangle_list()
+            .viewports_dynamic_scissors_irrelevant(1)
+            .fragment_shader(fs, specialization)
+            .render_pass(subpass);
+        let builder = if blend {
+            builder.blend_alpha_blending()
+        } else {
+            builder
+        };
+        let pipe = Arc::new(builder.build(Arc::clone(device))?);
+
+        let vbuf = CpuAccessibleBuffer::from_iter(
+            Arc::clone(device),
+            BufferUsage::vertex_buffer(),
+            [[-1.0, -1.0], [3.0, -1.0], [-1.0, 3.0]]
+                .iter()
+                .map(|&position| Vertex { position }),
+        )?;
+
+        Ok(FullscreenPipeline { pipe, vbuf })
+    }
+
+    pub fn pipeline(&self) -> &Arc<GraphicsPipelineAbstract + Send + Sync> {
+        &self.pipe
+    }
+
+    /// The layout of a descriptor set used by the fragment shader.
+    pub fn descriptor(&self, set: usize, binding: usize) -> Option<DescriptorDesc> {
+        self.pipe.descriptor(set, binding)
+    }
+
+    pub fn draw<S, Pc>(
+        &self,
+        cmd: AutoCommandBufferBuilder,
+        state: &DynamicState,
+        sets: S,
+        push_constants: Pc,
+    ) -> Fallible<AutoCommandBufferBuilder>
+    where
+        S: DescriptorSetsCollection,
+    {
+        let vbuf: Arc<BufferAccess + Send + Sync> = self.vbuf.clone();
+        Ok(cmd.draw(
+            Arc::clone(&self.pipe),
+            state,
+            vec![vbuf],
+            sets,
+            push_constants,
+        )?)
+    }
+}
+
+#[derive(Copy, Clone, Debug)]
+struct Vertex {
+    position: [f32; 2],
+}
+
+impl_vertex!(Vertex, position);
+
+#[allow(unused)]
+mod vs {
+    #[derive(VulkanoShader)]
+    #[ty = "vertex"]
+    #[path = "shader/post/vert.glsl"]
+    struct Dummy;
+}
@@ -0,0 +1,251 @@
+//! Post-processing applied to the 3D scene before the 2D overlay is drawn.
+//!
+//! The d3 subpass renders into an intermediate HDR image, which the post
+//! subpass reads as an input attachment and composites into the target with
+//! the current `Settings`. Custom `Effect`s are drawn afterwards in the same
+//! subpass. The d2 subpass runs last, so UI is unaffected.
+//!
+//! The whole stage can be turned off with the `PostProcessing` resource. The
+//! d3 subpass then renders straight into the target, and there is neither a
+//! scene image nor a post subpass to pay for.
+
+mod fullscreen;
+
+pub use self::fullscreen::FullscreenPipeline;
+
+use std::mem;
+use std::sync::Arc;
+
+use failure::{err_msg, Fallible};
+use vulkano::buffer::CpuBufferPool;
+use vulkano::command_buffer::{AutoCommandBufferBuilder, DynamicState};
+use vulkano::descriptor::descriptor_set::FixedSizeDescriptorSetsPool;
+use vulkano::device::Device;
+use vulkano::format::R16G16B16A16Sfloat;
+use vulkano::framebuffer::{RenderPassAbstract, Subpass};
+use vulkano::image::AttachmentImage;
+use vulkano::pipeline::GraphicsPipelineAbstract;
+
+use RenderStats;
+
+/// The intermediate image holding the rendered 3D scene.
+pub type SceneImage = AttachmentImage<R16G16B16A16Sfloat>;
+
+/// Whether frames have a post-processing stage.
+///
+/// If this resource exists when `init` is called it configures the render
+/// system, otherwise post-processing is enabled. While it is disabled the
+/// `Settings` are ignored, and effects and `Stage::Post` layers cannot be
+/// added.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct PostProcessing(pub bool);
+
+impl Default for PostProcessing {
+    fn default() -> Self {
+        PostProcessing(true)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Tonemap {
+    None = 0,
+    Reinhard = 1,
+    Aces = 2,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Vignette {
+    /// How much the corners are darkened, from 0 to 1.
+    pub strength: f32,
+    /// The distance from the centre of the screen at which darkening starts,
+    /// where 0.5 is the middle of an edge. A radius beyond the corners, at
+    /// about 0.71, leaves the whole screen undarkened.
+    pub radius: f32,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Settings {
+    pub exposure: f32,
+    pub tonemap: Tonemap,
+    /// Gamma to encode the output with. This should be left as `None` for
+    /// sRGB targets, which are gamma encoded by the hardware.
+    pub gamma: Option<f32>,
+    pub vignette: Option<Vignette>,
+}
+
+impl Default for Settings {
+    /// Settings that leave the scene unchanged.
+    fn default() -> Self {
+        Settings {
+            exposure: 1.0,
+            tonemap: Tonemap::None,
+            gamma: None,
+            vignette: None,
+        }
+    }
+}
+
+/// A custom post-processing effect.
+pub trait Effect: Send + Sync + 'static {
+    /// Create pipelines for the post-processing subpass.
+    fn build(
+        &mut self,
+        device: &Arc<Device>,
+        subpass: Subpass<Arc<RenderPassAbstract + Send + Sync>>,
+    ) -> Fallible<()>;
+
+    /// Record draw commands. `scene` should be bound as an input attachment to
+    /// read the 3D scene.
+    fn draw(
+        &mut self,
+        cmd: AutoCommandBufferBuilder,
+        state: &DynamicState,
+        scene: &Arc<SceneImage>,
+    ) -> Fallible<AutoCommandBufferBuilder>;
+}
+
+pub struct Renderer {
+    device: Arc<Device>,
+    /// The composite pipeline, or `None` if post-processing is disabled.
+    composite: Option<Composite>,
+    ubuf: CpuBufferPool<fs::ty::Data>,
+    settings: Settings,
+    effects: Vec<Box<Effect>>,
+}
+
+struct Composite {
+    subpass: Subpass<Arc<RenderPassAbstract + Send + Sync>>,
+    pipeline: FullscreenPipeline,
+    pool: FixedSizeDescriptorSetsPool<Arc<GraphicsPipelineAbstract + Send + Sync>>,
+}
+
+impl Renderer {
+    /// Create a renderer for the post subpass, or a disabled renderer if there
+    /// is none.
+    pub(crate) fn new(
+        device: &Arc<Device>,
+        subpass: Option<Subpass<Arc<RenderPassAbstract + Send + Sync>>>,
+    ) -> Fallible<Self> {
+        let composite = match subpass {
+            Some(subpass) => {
+                let fs = fs::Shader::load(Arc::clone(device))?;
+                let pipeline = FullscreenPipeline::new(
+                    device,
+                    subpass.clone(),
+                    fs.main_entry_point(),
+                    (),
+                    false,
+                )?;
+                let pool = FixedSizeDescriptorSetsPool::new(Arc::clone(pipeline.pipeline()), 0);
+                Some(Composite {
+                    subpass,
+                    pipeline,
+                    pool,
+                })
+            }
+            None => None,
+        };
+
+        Ok(Renderer {
+            device: Arc::clone(device),
+            composite,
+            ubuf: CpuBufferPool::uniform_buffer(Arc::clone(device)),
+            settings: Settings::default(),
+            effects: Vec::new(),
+        })
+    }
+
+    /// Whether frames have a post-processing stage.
+    pub fn is_enabled(&self) -> bool {
+        self.composite.is_some()
+    }
+
+    /// Move effects to a renderer for a new device, rebuilding their pipelines.
+    pub(crate) fn move_effects(&mut self, to: &mut Renderer) -> Fallible<()> {
+        for effect in self.effects.drain(..) {
+            to.push_effect(effect)?;
+        }
+        Ok(())
+    }
+
+    /// Composite the scene into the target and draw the effects. This must only
+    /// be called if post-processing is enabled.
+    pub(crate) fn commit(
+        &mut self,
+        mut cmd: AutoCommandBufferBuilder,
+        state: &DynamicState,
+        scene: &Arc<SceneImage>,
+        [width, height]: [u32; 2],
+        stats: &mut RenderStats,
+    ) -> Fallible<AutoCommandBufferBuilder> {
+        let settings = &self.settings;
+        let vignette = settings.vignette.unwrap_or(Vignette {
+            strength: 0.0,
+            radius: 0.0,
+        });
+        let ubuf = self.ubuf.next(fs::ty::Data {
+            dimensions: [width as f32, height as f32],
+            exposure: settings.exposure,
+            gamma: settings.gamma.unwrap_or(1.0),
+            vignette_strength: vignette.strength,
+            vignette_radius: vignette.radius,
+            tonemap: settings.tonemap as i32,
+        })?;
+        let composite = self
+            .composite
+            .as_mut()
+            .expect("post-processing is disabled");
+        let set = composite
+            .pool
+            .next()
+            .add_image(Arc::clone(scene))?
+            .add_buffer(ubuf)?
+            .build()?;
+        cmd = composite.pipeline.draw(cmd, state, set, ())?;
+        stats.draw_calls += 1;
+        stats.vertices += 3;
+        stats.uploaded_bytes += mem::size_of::<fs::ty::Data>();
+
+        for effect in &mut self.effects {
+            cmd = effect.draw(cmd, state, scene)?;
+        }
+
+        Ok(cmd)
+    }
+
+    pub fn settings(&self) -> &Settings {
+        &self.settings
+    }
+
+    pub fn set_settings(&mut self, settings: Settings) {
+        self.settings = settings;
+    }
+
+    /// Add an effect, drawn after the built-in effects and any effects added
+    /// previously. This fails if post-processing is disabled.
+    pub fn add_effect(&mut self, effect: impl Effect) -> Fallible<()> {
+        self.push_effect(Box::new(effect))
+    }
+
+    fn push_effect(&mut self, mut effect: Box<Effect>) -> Fallible<()> {
+        let subpass = match self.composite {
+            Some(ref composite) => composite.subpass.clone(),
+            None => return Err(err_msg("post-processing is disabled")),
+        };
+        effect.build(&self.device, subpass)?;
+        self.effects.push(effect);
+        Ok(())
+    }
+
+    pub fn clear_effects(&mut self) {
+        self.effects.clear();
+    }
+}
+
+#[allow(unused)]
+mod fs {
+    #[derive(VulkanoShader)]
+    #[ty = "fragment"]
+    #[path = "shader/post/frag.glsl"]
+    struct Dummy;
+}
@@ -0,0 +1,285 @@
+//! Reflection of the interface of a SPIR-V module.
+//!
+//! Only as much of the module is read as is needed to compare two compilations
+//! of a shader. Result ids differ between compilations, so types are described
+//! by their structure rather than by id.
+
+use std::collections::HashMap;
+
+use failure::{err_msg, Error, Fallible};
+
+const MAGIC: u32 = 0x0723_0203;
+const HEADER_LEN: usize = 5;
+
+const OP_ENTRY_POINT: u32 = 15;
+const OP_TYPE_VOID: u32 = 19;
+const OP_TYPE_VECTOR: u32 = 23;
+const OP_TYPE_MATRIX: u32 = 24;
+const OP_TYPE_IMAGE: u32 = 25;
+const OP_TYPE_SAMPLED_IMAGE: u32 = 27;
+const OP_TYPE_ARRAY: u32 = 28;
+const OP_TYPE_RUNTIME_ARRAY: u32 = 29;
+const OP_TYPE_STRUCT: u32 = 30;
+const OP_TYPE_POINTER: u32 = 32;
+const OP_CONSTANT: u32 = 43;
+const OP_VARIABLE: u32 = 59;
+const OP_DECORATE: u32 = 71;
+const OP_MEMBER_DECORATE: u32 = 72;
+
+const STORAGE_UNIFORM_CONSTANT: u32 = 0;
+const STORAGE_INPUT: u32 = 1;
+const STORAGE_UNIFORM: u32 = 2;
+const STORAGE_OUTPUT: u32 = 3;
+const STORAGE_PUSH_CONSTANT: u32 = 9;
+const STORAGE_BUFFER: u32 = 12;
+
+/// The decorations that place a variable in the interface: `BuiltIn`,
+/// `Location`, `Component`, `Binding` and `DescriptorSet`.
+const VARIABLE_DECORATIONS: [u32; 5] = [11, 30, 31, 33, 34];
+
+/// The inputs, outputs, descriptors and push constants of the `main` entry
+/// point of a shader. Shaders with the same interface can be used with the same
+/// pipeline.
+#[derive(Debug, PartialEq, Eq)]
+pub(in reload) struct Interface(Vec<String>);
+
+impl Interface {
+    pub(in reload) fn reflect(spirv: &[u8]) -> Fallible<Self> {
+        Module::parse(&words(spirv)?)?.interface()
+    }
+}
+
+#[derive(Default)]
+struct Module {
+    /// The input and output variables of the entry point.
+    entry_point: Option<Vec<u32>>,
+    /// Type declarations, by result id, with their operands.
+    types: HashMap<u32, (u32, Vec<u32>)>,
+    constants: HashMap<u32, u32>,
+    /// Variables, as their id, type and storage class.
+    variables: Vec<(u32, u32, u32)>,
+    decorations: HashMap<u32, Vec<Vec<u32>>>,
+    member_decorations: HashMap<(u32, u32), Vec<Vec<u32>>>,
+}
+
+impl Module {
+    fn parse(words: &[u32]) -> Fallible<Self> {
+        let mut module = Module::default();
+        let mut pos = HEADER_LEN;
+        while pos < words.len() {
+            let (len, op) = ((words[pos] >> 16) as usize, words[pos] & 0xffff);
+            if len == 0 || pos + len > words.len() {
+                return Err(invalid());
+            }
+            let args = &words[pos + 1..pos + len];
+            pos += len;
+
+            match op {
+                OP_ENTRY_POINT if args.len() > 2 => {
+                    let (name, interface) = string(&args[2..])?;
+                    if name == "main" {
+                        module.entry_point = Some(interface.to_vec());
+                    }
+                }
+                OP_TYPE_VOID..=OP_TYPE_POINTER if !args.is_empty() => {
+                    module.types.insert(args[0], (op, args[1..].to_vec()));
+                }
+                OP_CONSTANT if args.len() > 2 => {
+                    module.constants.insert(args[1], args[2]);
+                }
+                OP_VARIABLE if args.len() > 2 => {
+                    module.variables.push((args[1], args[0], args[2]));
+                }
+                OP_DECORATE if args.len() > 1 => {
+                    module
+                        .decorations
+                        .entry(args[0])
+                        .or_insert_with(Vec::new)
+                        .push(args[1..].to_vec());
+                }
+                OP_MEMBER_DECORATE if args.len() > 2 => {
+                    module
+                        .member_decorations
+                        .entry((args[0], args[1]))
+                        .or_insert_with(Vec::new)
+                        .push(args[2..].to_vec());
+                }
+                _ => (),
+            }
+        }
+        Ok(module)
+    }
+
+    fn interface(&self) -> Fallible<Interface> {
+        let entry_point = self
+            .entry_point
+            .as_ref()
+            .ok_or_else(|| err_msg("shader has no main entry point"))?;
+
+        let mut entries = Vec::new();
+        for &(id, ty, storage) in &self.variables {
+            let used = match storage {
+                STORAGE_INPUT | STORAGE_OUTPUT => entry_point.contains(&id),
+                STORAGE_UNIFORM_CONSTANT
+                | STORAGE_UNIFORM
+                | STORAGE_PUSH_CONSTANT
+                | STORAGE_BUFFER => true,
+                _ => false,
+            };
+            if !used {
+                continue;
+            }
+
+            let mut decorations: Vec<&Vec<u32>> = self
+                .decorations
+                .get(&id)
+                .into_iter()
+                .flat_map(|decorations| decorations)
+                .filter(|decoration| VARIABLE_DECORATIONS.contains(&decoration[0]))
+                .collect();
+            decorations.sort();
+            entries.push(format!("{:?} {}", decorations, self.describe(ty)?));
+        }
+        entries.sort();
+        Ok(Interface(entries))
+    }
+
+    /// Describe a type by its structure and decorations.
+    fn describe(&self, id: u32) -> Fallible<String> {
+        let (op, ref args) = *self.types.get(&id).ok_or_else(invalid)?;
+        let arg = |idx: usize| args.get(idx).cloned().ok_or_else(invalid);
+
+        let mut desc = match op {
+            OP_TYPE_VECTOR => format!("vec{}<{}>", arg(1)?, self.describe(arg(0)?)?),
+            OP_TYPE_MATRIX => format!("mat{}<{}>", arg(1)?, self.describe(arg(0)?)?),
+            OP_TYPE_IMAGE => format!("image<{}>{:?}", self.describe(arg(0)?)?, &args[1..]),
+            OP_TYPE_SAMPLED_IMAGE => format!("sampled<{}>", self.describe(arg(0)?)?),
+            OP_TYPE_ARRAY => {
+                let len = self.constants.get(&arg(1)?).ok_or_else(invalid)?;
+                format!("[{}; {}]", self.describe(arg(0)?)?, len)
+            }
+            OP_TYPE_RUNTIME_ARRAY => format!("[{}]", self.describe(arg(0)?)?),
+            OP_TYPE_POINTER => format!("ptr{}<{}>", arg(0)?, self.describe(arg(1)?)?),
+            OP_TYPE_STRUCT => {
+                let mut members = Vec::with_capacity(args.len());
+                for (idx, &member) in args.iter().enumerate() {
+                    let decorations = self.member_decorations.get(&(id, idx as u32));
+                    members.push(format!(
+                        "{:?} {}",
+                        sorted(decorations),
+                        self.describe(member)?
+                    ));
+                }
+                format!("struct {{ {} }}", members.join(", "))
+            }
+            // Scalars and samplers have no operands that are ids.
+            _ => format!("op{}{:?}", op, args),
+        };
+        if let Some(decorations) = self.decorations.get(&id) {
+            desc += &format!(" {:?}", sorted(Some(decorations)));
+        }
+        Ok(desc)
+    }
+}
+
+fn sorted(decorations: Option<&Vec<Vec<u32>>>) -> Vec<Vec<u32>> {
+    let mut decorations = decorations.cloned().unwrap_or_default();
+    decorations.sort();
+    decorations
+}
+
+/// Split SPIR-V into words, in either byte order.
+fn words(bytes: &[u8]) -> Fallible<Vec<u32>> {
+    if bytes.len() % 4 != 0 || bytes.len() < 4 * HEADER_LEN {
+        return Err(invalid());
+    }
+
+    let words: Vec<u32> = bytes
+        .chunks(4)
+        .map(|b| {
+            u32::from(b[0]) | u32::from(b[1]) << 8 | u32::from(b[2]) << 16 | u32::from(b[3]) << 24
+        })
+        .collect();
+    if words[0] == MAGIC {
+        Ok(words)
+    } else if words[0].swap_bytes() == MAGIC {
+        Ok(words.into_iter().map(u32::swap_bytes).collect())
+    } else {
+        Err(invalid())
+    }
+}
+
+/// Read a nul-terminated string literal, returning it and the remaining words.
+fn string(words: &[u32]) -> Fallible<(String, &[u32])> {
+    let mut bytes = Vec::new();
+    for (idx, &word) in words.iter().enumerate() {
+        for &shift in &[0, 8, 16, 24] {
+            let byte = (word >> shift) as u8;
+            if byte == 0 {
+                let string = String::from_utf8(bytes).map_err(|_| invalid())?;
+                return Ok((string, &words[idx + 1..]));
+            }
+            bytes.push(byte);
+        }
+    }
+    Err(invalid())
+}
+
+fn invalid() -> Error {
+    err_msg("invalid SPIR-V")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{to_spirv, ShaderType};
+    use super::*;
+
+    const SHADER: &str = "
+        #version 450
+
+        layout(location = 0) in vec2 position;
+        layout(location = 1) in vec4 color;
+
+        layout(location = 0) out vec4 v_color;
+
+        layout(set = 0, binding = 0) uniform Data {
+            mat4 transform;
+        } uniforms;
+
+        void main() {
+            v_color = color;
+            gl_Position = uniforms.transform * vec4(position, 0.0, 1.0);
+        }
+    ";
+
+    fn reflect(source: &str) -> Interface {
+        Interface::reflect(&to_spirv(source, ShaderType::Vertex).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn same_interface() {
+        let body = SHADER.replace("v_color = color;", "v_color = color * 0.5;");
+        assert_eq!(reflect(SHADER), reflect(&body));
+    }
+
+    #[test]
+    fn changed_interface() {
+        let expected = reflect(SHADER);
+        let changes = [
+            ("in vec2 position", "in ivec2 position"),
+            ("location = 1) in", "location = 2) in"),
+            ("location = 0) out", "location = 3) out"),
+            ("binding = 0", "binding = 1"),
+            ("mat4 transform;", "vec4 offset; mat4 transform;"),
+        ];
+        for &(from, to) in &changes {
+            assert_ne!(reflect(&SHADER.replace(from, to)), expected, "{} -> {}", from, to);
+        }
+    }
+
+    #[test]
+    fn invalid_spirv() {
+        assert!(Interface::reflect(&[]).is_err());
+        assert!(Interface::reflect(&[0; 24]).is_err());
+    }
+}
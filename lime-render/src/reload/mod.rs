@@ -0,0 +1,122 @@
+//! Shader hot reloading, enabled by the default `hot-reload` feature in debug
+//! builds.
+//!
+//! The render system watches the GLSL sources in `shader/d2` and `shader/d3`,
+//! recompiles them when they change and rebuilds the affected pipelines in
+//! place. If a shader fails to compile, the old pipeline is kept and the
+//! compiler output is logged.
+//!
+//! A reloaded shader is used with the interface of the shader compiled into the
+//! crate, so it is rejected if its inputs, outputs, descriptors or push
+//! constants differ from those of the compiled-in shader. Such changes still
+//! need a rebuild. Pipelines rebuilt after device loss use the compiled-in
+//! shaders until the sources change again.
+
+mod interface;
+
+use std::fs::{self, File};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+
+use failure::{err_msg, Fallible};
+use glsl_to_spirv;
+use vulkano::device::Device;
+use vulkano::pipeline::shader::ShaderModule;
+
+use self::interface::Interface;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) enum ShaderType {
+    Vertex,
+    Fragment,
+}
+
+/// Watches a set of shader sources for changes.
+pub(crate) struct Watcher {
+    files: Vec<(PathBuf, Option<SystemTime>)>,
+    last_poll: Instant,
+}
+
+impl Watcher {
+    /// Watch shader sources, given as paths relative to the `shader` directory.
+    pub(crate) fn new(paths: &[&str]) -> Self {
+        let files = paths
+            .iter()
+            .map(|path| {
+                let path = source_path(path);
+                let modified = modified(&path);
+                (path, modified)
+            }).collect();
+        Watcher {
+            files,
+            last_poll: Instant::now(),
+        }
+    }
+
+    /// Check whether any of the sources have changed since the last call.
+    pub(crate) fn changed(&mut self) -> bool {
+        if self.last_poll.elapsed() < POLL_INTERVAL {
+            return false;
+        }
+        self.last_poll = Instant::now();
+
+        let mut changed = false;
+        for &mut (ref path, ref mut last) in &mut self.files {
+            let modified = modified(path);
+            if modified.is_some() && modified != *last {
+                *last = modified;
+                changed = true;
+            }
+        }
+        changed
+    }
+}
+
+/// Compile a shader source, given as a path relative to the `shader` directory.
+/// The shader is rejected unless its interface matches that of `compiled`, the
+/// source of the shader compiled into the crate.
+pub(crate) fn compile(
+    device: &Arc<Device>,
+    path: &str,
+    compiled: &str,
+    ty: ShaderType,
+) -> Fallible<Arc<ShaderModule>> {
+    let path = source_path(path);
+    let source = fs::read_to_string(&path)?;
+    let spirv = to_spirv(&source, ty)
+        .map_err(|err| err_msg(format!("{}:\n{}", path.display(), err)))?;
+
+    if Interface::reflect(&spirv)? != Interface::reflect(&to_spirv(compiled, ty)?)? {
+        return Err(err_msg(format!(
+            "{}: the shader interface has changed, so a rebuild is needed to use it",
+            path.display()
+        )));
+    }
+
+    // The SPIR-V was just produced by the compiler, so it is valid, and its
+    // interface is the one the pipeline was created with.
+    Ok(unsafe { ShaderModule::new(Arc::clone(device), &spirv)? })
+}
+
+fn to_spirv(source: &str, ty: ShaderType) -> Fallible<Vec<u8>> {
+    let ty = match ty {
+        ShaderType::Vertex => glsl_to_spirv::ShaderType::Vertex,
+        ShaderType::Fragment => glsl_to_spirv::ShaderType::Fragment,
+    };
+    let mut spirv: File = glsl_to_spirv::compile(source, ty).map_err(err_msg)?;
+    let mut bytes = Vec::new();
+    spirv.read_to_end(&mut bytes)?;
+    Ok(bytes)
+}
+
+fn source_path(path: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("shader").join(path)
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|meta| meta.modified()).ok()
+}
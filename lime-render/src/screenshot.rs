@@ -0,0 +1,91 @@
+use std::fs::File;
+use std::path::Path;
+
+use failure::{err_msg, Fallible};
+use image::png::PNGEncoder;
+use image::ColorType;
+use vulkano::format::Format;
+
+/// A frame read back from a render target, stored as tightly packed RGBA8 rows.
+///
+/// The bytes are in the same encoding the target displays, so frames captured
+/// from an sRGB surface are already gamma-encoded and can be written to an
+/// image file directly.
+#[derive(Clone, Debug)]
+pub struct Screenshot {
+    data: Vec<u8>,
+    dimensions: [u32; 2],
+}
+
+impl Screenshot {
+    pub(crate) fn from_raw(
+        mut data: Vec<u8>,
+        dimensions: [u32; 2],
+        format: Format,
+    ) -> Fallible<Self> {
+        match format {
+            Format::R8G8B8A8Unorm
+            | Format::R8G8B8A8Srgb
+            | Format::A8B8G8R8UnormPack32
+            | Format::A8B8G8R8SrgbPack32 => (),
+            Format::B8G8R8A8Unorm | Format::B8G8R8A8Srgb => {
+                for pixel in data.chunks_mut(4) {
+                    pixel.swap(0, 2);
+                }
+            }
+            format => {
+                return Err(err_msg(format!(
+                    "cannot capture from target with format {:?}",
+                    format
+                )))
+            }
+        }
+
+        let [width, height] = dimensions;
+        debug_assert_eq!(data.len(), 4 * width as usize * height as usize);
+        Ok(Screenshot { data, dimensions })
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn into_data(self) -> Vec<u8> {
+        self.data
+    }
+
+    pub fn dimensions(&self) -> [u32; 2] {
+        self.dimensions
+    }
+
+    pub fn save_png(&self, path: impl AsRef<Path>) -> Fallible<()> {
+        let [width, height] = self.dimensions;
+        PNGEncoder::new(File::create(path)?).encode(
+            &self.data,
+            width,
+            height,
+            ColorType::RGBA(8),
+        )?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn swizzle() {
+        let bgra = vec![1, 2, 3, 4, 5, 6, 7, 8];
+        let shot = Screenshot::from_raw(bgra.clone(), [2, 1], Format::B8G8R8A8Srgb).unwrap();
+        assert_eq!(shot.data(), &[3, 2, 1, 4, 7, 6, 5, 8]);
+        let shot = Screenshot::from_raw(bgra.clone(), [1, 2], Format::B8G8R8A8Unorm).unwrap();
+        assert_eq!(shot.data(), &[3, 2, 1, 4, 7, 6, 5, 8]);
+        assert_eq!(shot.dimensions(), [1, 2]);
+
+        let shot = Screenshot::from_raw(bgra.clone(), [2, 1], Format::R8G8B8A8Srgb).unwrap();
+        assert_eq!(shot.data(), &bgra[..]);
+
+        assert!(Screenshot::from_raw(bgra, [2, 1], Format::R16G16Unorm).is_err());
+    }
+}
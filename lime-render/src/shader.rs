@@ -0,0 +1,27 @@
+//! Shader entry points.
+//!
+//! The built-in renderers build their pipelines from shader modules rather than
+//! the entry points generated by `VulkanoShader`, so that with shader hot
+//! reloading a pipeline can be rebuilt from a module compiled at runtime (see
+//! `reload`).
+
+/// Create an entry point for the `main` function of a shader module, using the
+/// interface generated by `VulkanoShader` in the module `$shader`.
+///
+/// This must be called in an unsafe block, and the module must have been
+/// compiled from a shader with the same interface, for example one checked by
+/// `reload::compile`.
+macro_rules! main_entry_point {
+    ($module:expr, $shader:ident, $stage:ident, $ty:ident) => {
+        $module.graphics_entry_point(
+            ::std::ffi::CStr::from_bytes_with_nul_unchecked(b"main\0"),
+            $shader::MainInput,
+            $shader::MainOutput,
+            $shader::Layout(::vulkano::descriptor::descriptor::ShaderStages {
+                $stage: true,
+                ..::vulkano::descriptor::descriptor::ShaderStages::none()
+            }),
+            ::vulkano::pipeline::shader::GraphicsShaderType::$ty,
+        )
+    };
+}
@@ -0,0 +1,69 @@
+//! Per-frame render statistics.
+
+/// Counters describing the last frame rendered, published as a resource.
+///
+/// Only work done by the built-in renderers is counted; custom layers and
+/// post-processing effects record their own commands and are not included.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RenderStats {
+    /// The number of draw commands recorded.
+    pub draw_calls: usize,
+    /// The number of vertices submitted for drawing. Instanced meshes count
+    /// their vertices once per instance.
+    pub vertices: usize,
+    /// The number of indices submitted for drawing, counted like `vertices`.
+    pub indices: usize,
+    /// The number of d2 sections drawn, after merging.
+    pub sections: usize,
+    /// The number of glyphs queued on the d2 renderer.
+    pub glyphs: usize,
+    /// The number of mesh instances drawn, after culling.
+    pub meshes: usize,
+    /// The number of bytes written to host-visible buffers for the frame,
+    /// including uniforms, instance data and d2 geometry.
+    pub uploaded_bytes: usize,
+    /// The number of times the swapchain has been recreated since the render
+    /// system started. Unlike the other counters, this is not reset each frame.
+    pub swapchain_recreations: usize,
+}
+
+impl RenderStats {
+    pub(crate) fn new() -> Self {
+        RenderStats::default()
+    }
+
+    /// Reset the per-frame counters.
+    pub(crate) fn begin_frame(&mut self) {
+        *self = RenderStats {
+            swapchain_recreations: self.swapchain_recreations,
+            ..RenderStats::default()
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn begin_frame() {
+        let mut stats = RenderStats {
+            draw_calls: 3,
+            vertices: 30,
+            indices: 36,
+            sections: 1,
+            glyphs: 5,
+            meshes: 2,
+            uploaded_bytes: 256,
+            swapchain_recreations: 1,
+        };
+        stats.begin_frame();
+        assert_eq!(
+            stats,
+            RenderStats {
+                swapchain_recreations: 1,
+                ..RenderStats::default()
+            }
+        );
+    }
+}
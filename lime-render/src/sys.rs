@@ -1,28 +1,75 @@
+use std::cmp;
 use std::marker::PhantomData;
 use std::sync::Arc;
 
-use failure::Fallible;
+use failure::{err_msg, Fallible};
 use shrev::{EventChannel, ReaderId};
 use specs::prelude::*;
-use utils::throw;
 use vulkano::command_buffer::{AutoCommandBuffer, AutoCommandBufferBuilder, DynamicState};
-use vulkano::framebuffer::Subpass;
+use vulkano::framebuffer::{FramebufferAbstract, RenderPassAbstract, Subpass};
 use vulkano::instance::{Instance, PhysicalDevice};
 use vulkano::pipeline::viewport::Viewport;
 use vulkano::sync::GpuFuture;
 use vulkano_win;
 use winit::{self, WindowEvent};
 
-use {d2, d3, Context, Target};
+use frames::{Fence, FrameSlots, FramesInFlight};
+use layer::{Frame, Layers, Stage};
+use post::PostProcessing;
+use timestamps::FrameTimer;
+#[cfg(all(feature = "hot-reload", debug_assertions))]
+use reload;
+use {d2, d3, post, Context, RenderError, RenderStats, Target, Timings, Uploads};
+
+const MAX_ATTEMPTS: usize = 5;
 
 pub(crate) struct RenderSystem<T> {
     prev_frame: Option<Box<GpuFuture + Send + Sync>>,
-    swapchain_dirty: bool,
+    timer: Option<FrameTimer>,
+    status: Status,
     event_rx: ReaderId<winit::Event>,
-    state: DynamicState,
+    #[cfg(all(feature = "hot-reload", debug_assertions))]
+    shaders: Shaders,
     _target: PhantomData<T>,
 }
 
+/// Watches the shader sources of the built-in renderers.
+#[cfg(all(feature = "hot-reload", debug_assertions))]
+struct Shaders {
+    d3: reload::Watcher,
+    d2: reload::Watcher,
+}
+
+/// What must happen before the next frame can be rendered, ordered by severity.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Status {
+    Ready,
+    SwapchainDirty,
+    DeviceLost,
+    SurfaceLost,
+}
+
+impl Status {
+    /// The status needed to recover from `err`.
+    fn from_error(err: &RenderError) -> Self {
+        match err {
+            RenderError::OutOfDate | RenderError::Other(_) => Status::SwapchainDirty,
+            RenderError::OutOfMemory => Status::Ready,
+            RenderError::DeviceLost => Status::DeviceLost,
+            RenderError::SurfaceLost => Status::SurfaceLost,
+        }
+    }
+
+    /// Record a failure that needs `next` to recover from. Returns whether to
+    /// retry in the same frame.
+    fn fail(&mut self, next: Status) -> bool {
+        // Only retry failed draws. If recovery failed, try again next frame.
+        let retry = *self == Status::Ready && next != Status::Ready;
+        *self = cmp::max(*self, next);
+        retry
+    }
+}
+
 impl<T> RenderSystem<T>
 where
     T: Target,
@@ -33,134 +80,208 @@ where
         world: &mut World,
         dispatcher: &mut DispatcherBuilder,
         data: T::InitData,
-        d3_sys: &str,
-        d2_sys: &str,
-    ) {
+        deps: &[&str],
+    ) -> Fallible<()> {
         let instance = {
             let extensions = vulkano_win::required_extensions();
-            Instance::new(None, &extensions, None).unwrap_or_else(throw)
+            Instance::new(None, &extensions, None)?
         };
 
         let phys = PhysicalDevice::enumerate(&instance)
             .next()
-            .expect("no device available");
+            .ok_or_else(|| err_msg("no device available"))?;
         info!("Using device: {} (type: {:?}).", phys.name(), phys.ty());
 
-        let (target, ctx) = T::new(phys, data).unwrap_or_else(throw);
+        let PostProcessing(post) = world
+            .res
+            .try_fetch::<PostProcessing>()
+            .map(|post| *post)
+            .unwrap_or_default();
+        let (target, ctx) = T::new(phys, data, post)?;
+
+        let frames = world
+            .res
+            .try_fetch::<FramesInFlight>()
+            .map(|frames| *frames)
+            .unwrap_or_default();
+        let frames = FrameSlots::new(frames);
 
         let event_rx = world
             .write_resource::<EventChannel<winit::Event>>()
             .register_reader();
 
-        let [w, h] = target.dimensions();
-        let state = DynamicState {
-            line_width: None,
-            viewports: Some(vec![Viewport {
-                origin: [0.0, 0.0],
-                dimensions: [w as f32, h as f32],
-                depth_range: 0.0..1.0,
-            }]),
-            scissors: None,
-        };
-
-        world.add_resource(d3::Renderer::new(
-            ctx.device(),
-            Subpass::from(Arc::clone(target.render_pass()), 0).unwrap(),
-        ));
-        world.add_resource(d2::Renderer::new(
-            ctx.device(),
-            Subpass::from(Arc::clone(target.render_pass()), 1).unwrap(),
-        ));
+        let layers = Layers::new(ctx.device(), target.render_pass(), post);
+        let (d3, post, d2) = create_renderers(&ctx, &target, frames.count())?;
+        let timer = FrameTimer::new(&ctx, frames.count());
+        world.add_resource(d3);
+        world.add_resource(post);
+        world.add_resource(d2);
         world.add_resource(ctx);
         world.add_resource(target);
+        world.add_resource(Uploads::new());
+        world.add_resource(layers);
+        world.add_resource(frames);
+        world.add_resource(Timings::new());
+        world.add_resource(RenderStats::new());
+        world.add_resource(d3::Debug::new());
+        world.add_resource(EventChannel::<RenderError>::new());
 
         dispatcher.add(
             RenderSystem {
                 prev_frame: None,
-                swapchain_dirty: false,
+                timer,
+                status: Status::Ready,
                 event_rx,
-                state,
+                #[cfg(all(feature = "hot-reload", debug_assertions))]
+                shaders: Shaders {
+                    d3: reload::Watcher::new(&[d3::VERT_PATH, d3::FRAG_PATH]),
+                    d2: reload::Watcher::new(&[d2::VERT_PATH, d2::FRAG_PATH]),
+                },
                 _target: PhantomData::<T>,
             },
             Self::NAME,
-            &[d3_sys, d2_sys],
-        )
+            deps,
+        );
+        Ok(())
     }
 
-    fn render(
-        &mut self,
-        ctx: &Context,
-        target: &mut T,
-        d3: &mut d3::Renderer,
-        d2: &mut d2::Renderer,
-    ) {
-        for _ in 0..5 {
-            if self.swapchain_dirty {
-                match self.recreate_swapchain(ctx, target) {
+    fn render(&mut self, errors: &mut EventChannel<RenderError>, res: &mut Resources<T>) {
+        for _ in 0..MAX_ATTEMPTS {
+            let result = match self.status {
+                Status::Ready => match self.try_render(res) {
                     Ok(()) => {
-                        trace!("Recreate swapchain succeeded");
-                        self.swapchain_dirty = false;
-                    }
-                    Err(err) => {
-                        trace!("Recreate swapchain failed: {}.", err);
-                        break;
+                        trace!("Draw succeeded.");
+                        return;
                     }
+                    Err(err) => Err(err),
+                },
+                Status::SwapchainDirty => self.recreate_swapchain(res),
+                Status::DeviceLost => self.recover(res),
+                Status::SurfaceLost => break,
+            };
+
+            match result {
+                Ok(()) => {
+                    trace!("Recovered from {:?}.", self.status);
+                    self.status = Status::Ready;
                 }
-            } else {
-                match self.try_render(ctx, target, d3, d2) {
-                    Ok(()) => {
-                        trace!("Draw succeeded.");
-                        break;
+                Err(err) => {
+                    let err = RenderError::from(err);
+                    match err {
+                        RenderError::OutOfDate => debug!("Render failed: {}.", err),
+                        RenderError::SurfaceLost => error!("Render failed: {}.", err),
+                        _ => warn!("Render failed: {}.", err),
                     }
-                    Err(err) => {
-                        trace!("Draw failed: {}.", err);
-                        self.swapchain_dirty = true;
+                    let retry = self.status.fail(Status::from_error(&err));
+                    errors.single_write(err);
+                    if !retry {
+                        break;
                     }
                 }
             }
         }
+
+        // The frame was skipped, so what was queued for it is dropped rather
+        // than drawn with the next one.
+        res.d3.discard();
+        res.d2.discard();
     }
 
-    fn recreate_swapchain(&mut self, ctx: &Context, target: &mut T) -> Fallible<()> {
-        target.recreate(ctx)?;
-        let [w, h] = target.dimensions();
-        self.state.viewports.as_mut().unwrap()[0].dimensions = [w as f32, h as f32];
+    fn recreate_swapchain(&mut self, res: &mut Resources<T>) -> Fallible<()> {
+        res.target.recreate(res.ctx)?;
+        res.stats.swapchain_recreations += 1;
+        res.d2.set_logical_size(res.target.logical_size());
         Ok(())
     }
 
-    fn try_render(
-        &mut self,
-        ctx: &Context,
-        target: &mut T,
-        d3: &mut d3::Renderer,
-        d2: &mut d2::Renderer,
-    ) -> Fallible<()> {
-        let (fb, acquire) = target.acquire(ctx)?;
+    fn recover(&mut self, res: &mut Resources<T>) -> Fallible<()> {
+        info!("Rebuilding context after device loss.");
+
+        // Frames in flight and uploads cannot complete on a lost device.
+        self.prev_frame = None;
+        self.timer = None;
+        res.frames.clear();
+        res.uploads.reset();
+
+        let ctx = res.target.recover(res.ctx)?;
+        let (mut d3, mut post, d2) = create_renderers(&ctx, res.target, res.frames.count())?;
+        d3.set_camera(res.d3.camera().cloned());
+        post.set_settings(*res.post.settings());
+        res.post.move_effects(&mut post)?;
+        res.layers.rebuild(ctx.device(), res.target.render_pass())?;
+
+        *res.d3 = d3;
+        *res.post = post;
+        *res.d2 = d2;
+        self.timer = FrameTimer::new(&ctx, res.frames.count());
+        *res.ctx = ctx;
+        Ok(())
+    }
+
+    /// Rebuild the pipelines of renderers whose shader sources have changed.
+    #[cfg(all(feature = "hot-reload", debug_assertions))]
+    fn reload_shaders(&mut self, res: &mut Resources<T>) {
+        if self.shaders.d3.changed() {
+            let subpass = stage_subpass(res.target, Stage::Scene).unwrap();
+            match res.d3.reload_shaders(res.ctx.device(), subpass) {
+                Ok(()) => info!("Reloaded d3 shaders."),
+                Err(err) => error!("Failed to reload d3 shaders: {}", err),
+            }
+        }
+        if self.shaders.d2.changed() {
+            let subpass = stage_subpass(res.target, Stage::Overlay).unwrap();
+            match res.d2.reload_shaders(res.ctx.device(), subpass) {
+                Ok(()) => info!("Reloaded d2 shaders."),
+                Err(err) => error!("Failed to reload d2 shaders: {}", err),
+            }
+        }
+    }
+
+    fn try_render(&mut self, res: &mut Resources<T>) -> Fallible<()> {
+        let frame_start = res.timings.begin_frame();
+        res.stats.begin_frame();
+        res.frames.wait()?;
+        let slot = res.frames.current();
+        let timed = res.timings.enabled();
+        if let Some(ref mut timer) = self.timer {
+            // The last frame rendered with this slot has finished, so its
+            // timestamps can be read.
+            if let Some(gpu_frame) = timer.take(slot) {
+                if timed {
+                    res.timings.set_gpu_frame(gpu_frame);
+                }
+            }
+        }
+        let (fb, acquire) = res.target.acquire(res.ctx)?;
 
         if let Some(ref mut last_frame) = self.prev_frame {
             last_frame.cleanup_finished();
         }
+        res.uploads.poll();
 
-        let command_buffer = AutoCommandBufferBuilder::primary_one_time_submit(
-            Arc::clone(ctx.device()),
-            ctx.graphics_queue().family(),
-        )?.begin_render_pass(
-            fb,
-            false,
-            vec![[0.0, 0.0, 0.0, 1.0].into(), 1f32.into()],
-        )?;
-        let command_buffer = d3.commit(command_buffer, &self.state)?.next_subpass(false)?;
-        let command_buffer = d2
-            .commit(command_buffer, &self.state, target.logical_size())?
-            .end_render_pass()?
-            .build()?;
-
-        self.prev_frame = Some(match self.prev_frame.take() {
+        let command_buffer = record_frame(res, fb)?;
+
+        // Wait for unfinished uploads, in case the frame uses them.
+        let acquire = res.uploads.join(acquire);
+        if timed {
+            if let Some(ref mut timer) = self.timer {
+                timer.start(slot);
+            }
+        }
+        let fence = match self.prev_frame.take() {
             Some(last_frame) => {
-                self.execute(ctx, target, last_frame.join(acquire), command_buffer)?
+                self.execute(res.ctx, res.target, last_frame.join(acquire), command_buffer)?
             }
-            None => self.execute(ctx, target, acquire, command_buffer)?,
-        });
+            None => self.execute(res.ctx, res.target, acquire, command_buffer)?,
+        };
+        if let Some(ref mut timer) = self.timer {
+            timer.end(slot);
+        }
+        // The next frame is joined with this one so that it may use the same
+        // attachments, while the slot keeps the fence to wait on.
+        self.prev_frame = Some(Box::new(Arc::clone(&fence)));
+        res.frames.finish(fence);
+        res.timings.end_frame(frame_start);
         Ok(())
     }
 
@@ -170,14 +291,137 @@ where
         target: &mut T,
         acquire_future: impl GpuFuture + Send + Sync + 'static,
         command_buffer: AutoCommandBuffer,
-    ) -> Fallible<Box<GpuFuture + Send + Sync>> {
+    ) -> Fallible<Fence> {
         let future = acquire_future
             .then_execute(Arc::clone(ctx.graphics_queue()), command_buffer)?
             .then_signal_fence();
         let future = target.present(ctx, future)?;
-        future.flush()?;
-        Ok(future)
+        Ok(Arc::new(future.then_signal_fence_and_flush()?))
+    }
+}
+
+/// The resources used to render a frame.
+pub(crate) struct Resources<'a, T: 'a> {
+    pub(crate) ctx: &'a mut Context,
+    pub(crate) target: &'a mut T,
+    pub(crate) frames: &'a mut FrameSlots,
+    pub(crate) uploads: &'a mut Uploads,
+    pub(crate) layers: &'a mut Layers,
+    pub(crate) timings: &'a mut Timings,
+    pub(crate) stats: &'a mut RenderStats,
+    pub(crate) d3: &'a mut d3::Renderer,
+    pub(crate) debug: &'a mut d3::Debug,
+    pub(crate) post: &'a mut post::Renderer,
+    pub(crate) d2: &'a mut d2::Renderer,
+}
+
+/// Record a frame into `framebuffer`, drawing everything queued on the
+/// renderers and layers. The frame uses the current slot of `res.frames`,
+/// which must have been waited on.
+pub(crate) fn record_frame<T: Target>(
+    res: &mut Resources<T>,
+    framebuffer: Arc<FramebufferAbstract + Send + Sync>,
+) -> Fallible<AutoCommandBuffer> {
+    let Resources {
+        ref ctx,
+        ref target,
+        ref frames,
+        ref uploads,
+        ref mut layers,
+        ref mut timings,
+        ref mut stats,
+        ref mut d3,
+        ref mut debug,
+        ref mut post,
+        ref mut d2,
+    } = *res;
+
+    let [w, h] = target.dimensions();
+    let state = DynamicState {
+        line_width: None,
+        viewports: Some(vec![Viewport {
+            origin: [0.0, 0.0],
+            dimensions: [w as f32, h as f32],
+            depth_range: 0.0..1.0,
+        }]),
+        scissors: None,
+    };
+    let frame = Frame {
+        state: &state,
+        dimensions: target.dimensions(),
+        logical_size: target.logical_size(),
+        slot: frames.current(),
+    };
+
+    let command_buffer = AutoCommandBufferBuilder::primary_one_time_submit(
+        Arc::clone(ctx.device()),
+        ctx.graphics_queue().family(),
+    )?.begin_render_pass(
+        framebuffer,
+        false,
+        vec![
+            [0.0, 0.0, 0.0, 1.0].into(),
+            [0.0, 0.0, 0.0, 1.0].into(),
+            1f32.into(),
+        ],
+    )?;
+
+    let command_buffer = layers.draw(command_buffer, Stage::Scene, true, &frame, timings)?;
+    let start = timings.start();
+    let command_buffer = d3.commit(command_buffer, frame.state, uploads, frame.dimensions, stats)?;
+    let command_buffer =
+        d3.commit_debug(command_buffer, frame.state, debug, frame.dimensions, stats)?;
+    timings.record("d3", start);
+    let mut command_buffer = layers
+        .draw(command_buffer, Stage::Scene, false, &frame, timings)?
+        .next_subpass(false)?;
+
+    if let Some(scene) = target.scene_image() {
+        command_buffer = layers.draw(command_buffer, Stage::Post, true, &frame, timings)?;
+        let start = timings.start();
+        command_buffer =
+            post.commit(command_buffer, frame.state, scene, frame.dimensions, stats)?;
+        timings.record("post", start);
+        command_buffer = layers
+            .draw(command_buffer, Stage::Post, false, &frame, timings)?
+            .next_subpass(false)?;
     }
+
+    let command_buffer = layers.draw(command_buffer, Stage::Overlay, true, &frame, timings)?;
+    let start = timings.start();
+    let command_buffer = d2.commit(command_buffer, &frame, stats)?;
+    timings.record("d2", start);
+    Ok(layers
+        .draw(command_buffer, Stage::Overlay, false, &frame, timings)?
+        .end_render_pass()?
+        .build()?)
+}
+
+fn create_renderers<T: Target>(
+    ctx: &Context,
+    target: &T,
+    frames: usize,
+) -> Fallible<(d3::Renderer, post::Renderer, d2::Renderer)> {
+    let subpass = |stage| stage_subpass(target, stage);
+    Ok((
+        d3::Renderer::new(ctx.device(), subpass(Stage::Scene).unwrap())?,
+        post::Renderer::new(ctx.device(), subpass(Stage::Post))?,
+        d2::Renderer::new(
+            ctx.device(),
+            subpass(Stage::Overlay).unwrap(),
+            frames,
+            target.logical_size(),
+        )?,
+    ))
+}
+
+/// The subpass of the target's render pass for `stage`, if it exists.
+fn stage_subpass<T: Target>(
+    target: &T,
+    stage: Stage,
+) -> Option<Subpass<Arc<RenderPassAbstract + Send + Sync>>> {
+    let index = stage.subpass(target.scene_image().is_some())?;
+    Some(Subpass::from(Arc::clone(target.render_pass()), index).unwrap())
 }
 
 impl<'a, T> System<'a> for RenderSystem<T>
@@ -186,23 +430,111 @@ where
 {
     type SystemData = (
         ReadExpect<'a, EventChannel<winit::Event>>,
-        ReadExpect<'a, Context>,
+        WriteExpect<'a, EventChannel<RenderError>>,
+        WriteExpect<'a, Context>,
         WriteExpect<'a, T>,
+        WriteExpect<'a, FrameSlots>,
+        WriteExpect<'a, Uploads>,
+        WriteExpect<'a, Layers>,
+        WriteExpect<'a, Timings>,
+        WriteExpect<'a, RenderStats>,
         WriteExpect<'a, d3::Renderer>,
+        WriteExpect<'a, d3::Debug>,
+        WriteExpect<'a, post::Renderer>,
         WriteExpect<'a, d2::Renderer>,
     );
 
-    fn run(&mut self, (event_tx, ctx, mut target, mut d3, mut d2): Self::SystemData) {
+    fn run(
+        &mut self,
+        (
+            event_tx,
+            mut errors,
+            mut ctx,
+            mut target,
+            mut frames,
+            mut uploads,
+            mut layers,
+            mut timings,
+            mut stats,
+            mut d3,
+            mut debug,
+            mut post,
+            mut d2,
+        ): Self::SystemData,
+    ) {
         for event in event_tx.read(&mut self.event_rx) {
-            if let winit::Event::WindowEvent {
-                event: WindowEvent::HiDpiFactorChanged(_factor),
-                ..
-            } = event
-            {
-                //                self.target.set_hidpi_factor(factor)
+            match event {
+                winit::Event::WindowEvent {
+                    event: WindowEvent::Resized(_),
+                    ..
+                } => {
+                    // Recreate the swapchain now rather than when it is next
+                    // out of date, so that the logical size given to d2 follows
+                    // the window.
+                    self.status = cmp::max(self.status, Status::SwapchainDirty);
+                }
+                winit::Event::WindowEvent {
+                    event: WindowEvent::HiDpiFactorChanged(_factor),
+                    ..
+                } => {
+                    //                self.target.set_hidpi_factor(factor)
+                }
+                _ => (),
             }
         }
 
-        self.render(&ctx, &mut target, &mut d3, &mut d2);
+        let mut res = Resources {
+            ctx: &mut ctx,
+            target: &mut target,
+            frames: &mut frames,
+            uploads: &mut uploads,
+            layers: &mut layers,
+            timings: &mut timings,
+            stats: &mut stats,
+            d3: &mut d3,
+            debug: &mut debug,
+            post: &mut post,
+            d2: &mut d2,
+        };
+        #[cfg(all(feature = "hot-reload", debug_assertions))]
+        self.reload_shaders(&mut res);
+        self.render(&mut errors, &mut res);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn status_from_error() {
+        let status = |err| Status::from_error(&err);
+        assert_eq!(status(RenderError::OutOfDate), Status::SwapchainDirty);
+        assert_eq!(status(RenderError::Other("".into())), Status::SwapchainDirty);
+        assert_eq!(status(RenderError::OutOfMemory), Status::Ready);
+        assert_eq!(status(RenderError::DeviceLost), Status::DeviceLost);
+        assert_eq!(status(RenderError::SurfaceLost), Status::SurfaceLost);
+    }
+
+    #[test]
+    fn status_fail() {
+        // A failed draw is retried after recovering.
+        let mut status = Status::Ready;
+        assert!(status.fail(Status::SwapchainDirty));
+        assert_eq!(status, Status::SwapchainDirty);
+
+        // A failed recovery waits for the next frame, keeping the most severe
+        // status.
+        assert!(!status.fail(Status::DeviceLost));
+        assert_eq!(status, Status::DeviceLost);
+        assert!(!status.fail(Status::SwapchainDirty));
+        assert_eq!(status, Status::DeviceLost);
+        assert!(!status.fail(Status::SurfaceLost));
+        assert_eq!(status, Status::SurfaceLost);
+
+        // Running out of memory skips the frame.
+        let mut status = Status::Ready;
+        assert!(!status.fail(Status::Ready));
+        assert_eq!(status, Status::Ready);
     }
 }
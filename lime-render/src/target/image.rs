@@ -0,0 +1,346 @@
+use std::collections::VecDeque;
+use std::sync::Arc;
+
+use failure::{err_msg, Fallible};
+use vulkano::buffer::{BufferUsage, CpuAccessibleBuffer};
+use vulkano::command_buffer::AutoCommandBufferBuilder;
+use vulkano::format::{Format, R8G8B8A8Srgb};
+use vulkano::framebuffer::{FramebufferAbstract, RenderPassAbstract};
+use vulkano::image::{Dimensions, ImageUsage, StorageImage};
+use vulkano::instance::{DeviceExtensions, PhysicalDevice};
+use vulkano::sync::{now, FenceSignalFuture, GpuFuture};
+
+use post::SceneImage;
+use target::{create_attachments, create_framebuffer, create_render_pass, Target};
+use {Context, Screenshot};
+
+type Fence = Arc<FenceSignalFuture<Box<GpuFuture + Send + Sync>>>;
+
+pub struct ImageTarget {
+    render_pass: Arc<RenderPassAbstract + Send + Sync>,
+    framebuffer: Arc<FramebufferAbstract + Send + Sync>,
+    scene: Option<Arc<SceneImage>>,
+    post: bool,
+    image: Arc<StorageImage<R8G8B8A8Srgb>>,
+    buffer: Arc<CpuAccessibleBuffer<[u8]>>,
+    fence: Option<Fence>,
+    capture: Option<CaptureRing>,
+}
+
+/// Readback buffers for capturing a sequence of frames.
+struct CaptureRing {
+    free: Vec<Arc<CpuAccessibleBuffer<[u8]>>>,
+    /// Frames read back early to free their buffers, oldest first. These are
+    /// all older than the pending frames.
+    ready: VecDeque<(u64, Screenshot)>,
+    pending: VecDeque<CapturedFrame>,
+    next_frame: u64,
+}
+
+struct CapturedFrame {
+    index: u64,
+    buffer: Arc<CpuAccessibleBuffer<[u8]>>,
+    fence: Fence,
+    dimensions: [u32; 2],
+}
+
+impl CaptureRing {
+    /// The number of frames that have not been taken.
+    fn len(&self) -> usize {
+        self.ready.len() + self.pending.len()
+    }
+
+    /// Get a buffer to copy the next frame into. If none are free, this waits
+    /// for the oldest pending frame and keeps it in memory instead.
+    fn next_buffer(&mut self) -> Fallible<Arc<CpuAccessibleBuffer<[u8]>>> {
+        if let Some(buffer) = self.free.pop() {
+            return Ok(buffer);
+        }
+
+        debug!("Capture ring is full, waiting for frame to be copied.");
+        let frame = self
+            .pending
+            .pop_front()
+            .ok_or_else(|| err_msg("capture ring has no buffers"))?;
+        let screenshot = frame.read()?;
+        self.ready.push_back((frame.index, screenshot));
+        Ok(frame.buffer)
+    }
+
+    /// Remove the oldest frame that has not been taken, waiting for it to be
+    /// copied if necessary.
+    fn pop(&mut self) -> Fallible<Option<(u64, Screenshot)>> {
+        if let Some(frame) = self.ready.pop_front() {
+            return Ok(Some(frame));
+        }
+
+        match self.pending.pop_front() {
+            Some(frame) => {
+                let screenshot = frame.read()?;
+                self.free.push(frame.buffer);
+                Ok(Some((frame.index, screenshot)))
+            }
+            None => Ok(None),
+        }
+    }
+}
+
+impl CapturedFrame {
+    fn read(&self) -> Fallible<Screenshot> {
+        self.fence.wait(None)?;
+        let data = self.buffer.read()?.to_vec();
+        Screenshot::from_raw(data, self.dimensions, Format::R8G8B8A8Srgb)
+    }
+}
+
+impl ImageTarget {
+    /// Resize the target. This stops any capture in progress.
+    pub fn resize(&mut self, ctx: &Context, dimensions: [u32; 2]) -> Fallible<()> {
+        let (image, buffer) = create(ctx, dimensions)?;
+        let (dbuf, scene) = create_attachments(ctx.device(), dimensions, self.post)?;
+        self.framebuffer = create_framebuffer(
+            Arc::clone(&self.render_pass),
+            Arc::clone(&image),
+            dbuf,
+            scene.clone(),
+        )?;
+        self.scene = scene;
+        self.image = image;
+        self.buffer = buffer;
+        self.capture = None;
+        Ok(())
+    }
+
+    pub fn read<R, T>(&mut self, read: R) -> Fallible<T>
+    where
+        R: FnOnce(&[u8], [u32; 2]) -> Fallible<T>,
+    {
+        if let Some(fence) = self.fence.take() {
+            fence.wait(None)?;
+        }
+
+        read(&self.buffer.read()?, self.dimensions())
+    }
+
+    /// Start copying every frame into a ring of `ring_size` readback buffers
+    /// instead of the buffer used by `read`. Frames can be copied while earlier
+    /// ones are still being read, so rendering does not wait on each copy.
+    ///
+    /// Captured frames should be taken with `take_captured` before the ring
+    /// fills up. Once it is full, rendering waits for the oldest frame to be
+    /// copied and keeps it in memory until it is taken. Frames are numbered
+    /// from 0.
+    pub fn start_capture(&mut self, ctx: &Context, ring_size: usize) -> Fallible<()> {
+        let free = (0..ring_size.max(1))
+            .map(|_| create_buffer(ctx, self.dimensions()))
+            .collect::<Fallible<_>>()?;
+        self.capture = Some(CaptureRing {
+            free,
+            ready: VecDeque::new(),
+            pending: VecDeque::new(),
+            next_frame: 0,
+        });
+        Ok(())
+    }
+
+    /// Stop capturing, discarding any frames that have not been taken.
+    pub fn stop_capture(&mut self) {
+        self.capture = None;
+    }
+
+    pub fn is_capturing(&self) -> bool {
+        self.capture.is_some()
+    }
+
+    /// The number of frames rendered since capturing started, including those
+    /// already taken, or `None` if not capturing.
+    pub fn frames_captured(&self) -> Option<u64> {
+        self.capture.as_ref().map(|ring| ring.next_frame)
+    }
+
+    /// Pass captured frames to `f` in order, oldest first, until no more than
+    /// `keep` are still pending. This only waits for the GPU if a frame that
+    /// must be taken has not finished copying.
+    pub fn take_captured<F>(&mut self, keep: usize, mut f: F) -> Fallible<()>
+    where
+        F: FnMut(u64, Screenshot) -> Fallible<()>,
+    {
+        let ring = self
+            .capture
+            .as_mut()
+            .ok_or_else(|| err_msg("not capturing"))?;
+        while ring.len() > keep {
+            match ring.pop()? {
+                Some((index, frame)) => f(index, frame)?,
+                None => break,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Target for ImageTarget {
+    type InitData = [u32; 2];
+
+    fn new(
+        phys: PhysicalDevice,
+        dimensions: Self::InitData,
+        post: bool,
+    ) -> Fallible<(Self, Context)> {
+        let ctx = Context::new(phys, |_| true, &DeviceExtensions::none())?;
+        let render_pass = create_render_pass(Arc::clone(ctx.device()), R8G8B8A8Srgb, post)?;
+
+        let (image, buffer) = create(&ctx, dimensions)?;
+        let (dbuf, scene) = create_attachments(ctx.device(), dimensions, post)?;
+        let framebuffer = create_framebuffer(
+            Arc::clone(&render_pass),
+            Arc::clone(&image),
+            dbuf,
+            scene.clone(),
+        )?;
+        Ok((
+            ImageTarget {
+                render_pass,
+                framebuffer,
+                scene,
+                post,
+                image,
+                buffer,
+                fence: None,
+                capture: None,
+            },
+            ctx,
+        ))
+    }
+
+    fn render_pass(&self) -> &Arc<RenderPassAbstract + Send + Sync> {
+        &self.render_pass
+    }
+
+    fn scene_image(&self) -> Option<&Arc<SceneImage>> {
+        self.scene.as_ref()
+    }
+
+    fn dimensions(&self) -> [u32; 2] {
+        [self.framebuffer.width(), self.framebuffer.height()]
+    }
+
+    fn hidpi_factor(&self) -> f32 {
+        1.0
+    }
+
+    fn recreate(&mut self, _: &Context) -> Fallible<()> {
+        Ok(())
+    }
+
+    fn recover(&mut self, ctx: &Context) -> Fallible<Context> {
+        let (target, ctx) =
+            ImageTarget::new(ctx.device().physical_device(), self.dimensions(), self.post)?;
+        *self = target;
+        Ok(ctx)
+    }
+
+    fn acquire(
+        &mut self,
+        ctx: &Context,
+    ) -> Fallible<(
+        Arc<FramebufferAbstract + Send + Sync>,
+        Box<GpuFuture + Send + Sync>,
+    )> {
+        Ok((
+            Arc::clone(&self.framebuffer),
+            Box::new(now(Arc::clone(ctx.device()))),
+        ))
+    }
+
+    fn present<F>(&mut self, ctx: &Context, fut: F) -> Fallible<Box<GpuFuture + Send + Sync>>
+    where
+        F: GpuFuture + Send + Sync + 'static,
+    {
+        let dimensions = self.dimensions();
+        let fence = match self.capture {
+            Some(ref mut ring) => {
+                let buffer = ring.next_buffer()?;
+                let fence = match copy_image(ctx, &self.image, &buffer, fut) {
+                    Ok(fence) => fence,
+                    Err(err) => {
+                        ring.free.push(buffer);
+                        return Err(err);
+                    }
+                };
+                ring.pending.push_back(CapturedFrame {
+                    index: ring.next_frame,
+                    buffer,
+                    fence: Arc::clone(&fence),
+                    dimensions,
+                });
+                ring.next_frame += 1;
+                fence
+            }
+            None => {
+                let fence = copy_image(ctx, &self.image, &self.buffer, fut)?;
+                self.fence = Some(Arc::clone(&fence));
+                fence
+            }
+        };
+
+        Ok(Box::new(fence))
+    }
+}
+
+/// Copy `image` into `buffer` once `fut` has completed.
+fn copy_image<F>(
+    ctx: &Context,
+    image: &Arc<StorageImage<R8G8B8A8Srgb>>,
+    buffer: &Arc<CpuAccessibleBuffer<[u8]>>,
+    fut: F,
+) -> Fallible<Fence>
+where
+    F: GpuFuture + Send + Sync + 'static,
+{
+    let command_buffer =
+        AutoCommandBufferBuilder::new(Arc::clone(ctx.device()), ctx.transfer_queue().family())?
+            .copy_image_to_buffer(Arc::clone(image), Arc::clone(buffer))?
+            .build()?;
+
+    let fut: Box<GpuFuture + Send + Sync> = Box::new(
+        fut.then_signal_semaphore()
+            .then_execute(Arc::clone(ctx.transfer_queue()), command_buffer)?,
+    );
+    Ok(Arc::new(fut.then_signal_fence_and_flush()?))
+}
+
+fn create(
+    ctx: &Context,
+    [width, height]: [u32; 2],
+) -> Fallible<(
+    Arc<StorageImage<R8G8B8A8Srgb>>,
+    Arc<CpuAccessibleBuffer<[u8]>>,
+)> {
+    let image = StorageImage::with_usage(
+        Arc::clone(ctx.device()),
+        Dimensions::Dim2d { width, height },
+        R8G8B8A8Srgb,
+        ImageUsage {
+            color_attachment: true,
+            transfer_source: true,
+            ..ImageUsage::none()
+        },
+        ctx.queue_families(),
+    )?;
+
+    let buf = create_buffer(ctx, [width, height])?;
+
+    Ok((image, buf))
+}
+
+fn create_buffer(
+    ctx: &Context,
+    [width, height]: [u32; 2],
+) -> Fallible<Arc<CpuAccessibleBuffer<[u8]>>> {
+    Ok(CpuAccessibleBuffer::from_iter(
+        Arc::clone(ctx.device()),
+        BufferUsage::transfer_destination(),
+        (0..4 * width * height).map(|_| 0),
+    )?)
+}
@@ -0,0 +1,182 @@
+mod image;
+mod swapchain;
+
+pub use self::image::ImageTarget;
+pub use self::swapchain::SwapchainTarget;
+
+use std::sync::Arc;
+
+use failure::Fallible;
+use vulkano::device::Device;
+use vulkano::format::{D16Unorm, FormatDesc, R16G16B16A16Sfloat};
+use vulkano::framebuffer::{
+    Framebuffer, FramebufferAbstract, FramebufferCreationError, RenderPassAbstract,
+};
+use vulkano::image::{AttachmentImage, ImageViewAccess};
+use vulkano::instance::PhysicalDevice;
+use vulkano::sync::GpuFuture;
+
+use post::SceneImage;
+use Context;
+
+pub trait Target: Sized + Send + Sync + 'static {
+    type InitData;
+
+    /// Create the target and a context for it. The render pass has a
+    /// post-processing subpass if `post` is set.
+    fn new(phys: PhysicalDevice, Self::InitData, post: bool) -> Fallible<(Self, Context)>;
+
+    fn render_pass(&self) -> &Arc<RenderPassAbstract + Send + Sync>;
+    /// The intermediate image the 3D scene is rendered into before
+    /// post-processing, or `None` if post-processing is disabled.
+    fn scene_image(&self) -> Option<&Arc<SceneImage>>;
+    fn dimensions(&self) -> [u32; 2];
+    fn hidpi_factor(&self) -> f32;
+
+    fn logical_size(&self) -> [f32; 2] {
+        let [w, h] = self.dimensions();
+        let f = self.hidpi_factor();
+        [w as f32 / f, h as f32 / f]
+    }
+
+    fn recreate(&mut self, ctx: &Context) -> Fallible<()>;
+    /// Recreate every device resource after the device has been lost,
+    /// returning the new `Context`.
+    fn recover(&mut self, ctx: &Context) -> Fallible<Context>;
+
+    fn acquire(
+        &mut self,
+        ctx: &Context,
+    ) -> Fallible<(
+        Arc<FramebufferAbstract + Send + Sync>,
+        Box<GpuFuture + Send + Sync>,
+    )>;
+    fn present<F>(&mut self, ctx: &Context, fut: F) -> Fallible<Box<GpuFuture + Send + Sync>>
+    where
+        F: GpuFuture + Send + Sync + 'static;
+}
+
+/// Create the render pass. With `post` the scene is rendered into an HDR image
+/// and composited into the target in a subpass of its own, otherwise it is
+/// rendered straight into the target.
+fn create_render_pass(
+    device: Arc<Device>,
+    format: impl FormatDesc,
+    post: bool,
+) -> Fallible<Arc<RenderPassAbstract + Send + Sync>> {
+    if !post {
+        return Ok(Arc::new(ordered_passes_renderpass!(device,
+                attachments: {
+                    color: {
+                        load: Clear,
+                        store: Store,
+                        format: format.format(),
+                        samples: 1,
+                    },
+                    depth: {
+                        load: Clear,
+                        store: DontCare,
+                        format: D16Unorm.format(),
+                        samples: 1,
+                    }
+                },
+                passes: [
+                    {
+                        color: [color],
+                        depth_stencil: {depth},
+                        input: []
+                    },
+                    {
+                        color: [color],
+                        depth_stencil: { },
+                        input: []
+                    }
+                ]
+        )?));
+    }
+
+    Ok(Arc::new(ordered_passes_renderpass!(device,
+            attachments: {
+                color: {
+                    load: Clear,
+                    store: Store,
+                    format: format.format(),
+                    samples: 1,
+                },
+                scene: {
+                    load: Clear,
+                    store: DontCare,
+                    format: R16G16B16A16Sfloat.format(),
+                    samples: 1,
+                },
+                depth: {
+                    load: Clear,
+                    store: DontCare,
+                    format: D16Unorm.format(),
+                    samples: 1,
+                }
+            },
+            passes: [
+                {
+                    color: [scene],
+                    depth_stencil: {depth},
+                    input: []
+                },
+                {
+                    color: [color],
+                    depth_stencil: { },
+                    input: [scene]
+                },
+                {
+                    color: [color],
+                    depth_stencil: { },
+                    input: []
+                }
+            ]
+    )?))
+}
+
+/// Create the depth buffer, and the scene image if `post` is set.
+fn create_attachments(
+    device: &Arc<Device>,
+    dimensions: [u32; 2],
+    post: bool,
+) -> Fallible<(Arc<AttachmentImage<D16Unorm>>, Option<Arc<SceneImage>>)> {
+    let dbuf = AttachmentImage::transient(Arc::clone(device), dimensions, D16Unorm)?;
+    let scene = if post {
+        Some(AttachmentImage::transient_input_attachment(
+            Arc::clone(device),
+            dimensions,
+            R16G16B16A16Sfloat,
+        )?)
+    } else {
+        None
+    };
+    Ok((dbuf, scene))
+}
+
+fn create_framebuffers<I: ImageViewAccess + Send + Sync + 'static>(
+    pass: &Arc<RenderPassAbstract + Send + Sync>,
+    images: impl IntoIterator<Item = Arc<I>>,
+    dbuf: &Arc<AttachmentImage<D16Unorm>>,
+    scene: Option<&Arc<SceneImage>>,
+) -> Result<Vec<Arc<FramebufferAbstract + Send + Sync>>, FramebufferCreationError> {
+    images
+        .into_iter()
+        .map(|img| create_framebuffer(Arc::clone(pass), img, Arc::clone(dbuf), scene.cloned()))
+        .collect()
+}
+
+fn create_framebuffer<I: ImageViewAccess + Send + Sync + 'static>(
+    pass: Arc<RenderPassAbstract + Send + Sync>,
+    img: Arc<I>,
+    dbuf: Arc<AttachmentImage<D16Unorm>>,
+    scene: Option<Arc<SceneImage>>,
+) -> Result<Arc<FramebufferAbstract + Send + Sync>, FramebufferCreationError> {
+    let framebuffer = Framebuffer::start(pass).add(img)?;
+    let framebuffer: Arc<FramebufferAbstract + Send + Sync> = match scene {
+        Some(scene) => Arc::new(framebuffer.add(scene)?.add(dbuf)?.build()?),
+        None => Arc::new(framebuffer.add(dbuf)?.build()?),
+    };
+    Ok(framebuffer)
+}
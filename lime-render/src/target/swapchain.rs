@@ -0,0 +1,321 @@
+use std::mem;
+use std::sync::Arc;
+
+use failure::{err_msg, Fallible};
+use vulkano::buffer::{BufferUsage, CpuAccessibleBuffer};
+use vulkano::command_buffer::AutoCommandBufferBuilder;
+use vulkano::format::Format;
+use vulkano::framebuffer::{FramebufferAbstract, RenderPassAbstract};
+use vulkano::image::SwapchainImage;
+use vulkano::instance::{DeviceExtensions, PhysicalDevice};
+use vulkano::swapchain::{self, ColorSpace, PresentMode, Surface, SurfaceTransform, Swapchain};
+use vulkano::sync::{FenceSignalFuture, GpuFuture};
+use vulkano_win;
+use winit::Window;
+
+use post::SceneImage;
+use target::{create_attachments, create_framebuffers, create_render_pass, Target};
+use {Context, Screenshot};
+
+pub struct SwapchainTarget {
+    surface: Arc<Surface<Window>>,
+    swapchain: Option<Arc<Swapchain<Window>>>,
+    render_pass: Arc<RenderPassAbstract + Send + Sync>,
+    images: Vec<Arc<SwapchainImage<Window>>>,
+    framebuffers: Vec<Arc<FramebufferAbstract + Send + Sync>>,
+    scene: Option<Arc<SceneImage>>,
+    post: bool,
+    index: Option<usize>,
+    dimensions: [u32; 2],
+    format: Format,
+    capture: Capture,
+    capture_supported: bool,
+}
+
+enum Capture {
+    Idle,
+    Requested,
+    Pending {
+        buffer: Arc<CpuAccessibleBuffer<[u8]>>,
+        fence: Arc<FenceSignalFuture<Box<GpuFuture + Send + Sync>>>,
+        dimensions: [u32; 2],
+    },
+}
+
+impl SwapchainTarget {
+    pub fn window(&self) -> &Window {
+        self.surface.window()
+    }
+
+    /// Request a copy of the next presented frame. The result can be retrieved
+    /// with `take_screenshot` once that frame has been rendered.
+    pub fn capture_next_frame(&mut self) {
+        if let Capture::Idle = self.capture {
+            self.capture = Capture::Requested;
+        }
+    }
+
+    /// Get the frame captured after a call to `capture_next_frame`, waiting for
+    /// the GPU to finish the copy if necessary. Returns `None` if no frame has
+    /// been captured yet.
+    pub fn take_screenshot(&mut self) -> Fallible<Option<Screenshot>> {
+        match mem::replace(&mut self.capture, Capture::Idle) {
+            Capture::Idle => Ok(None),
+            Capture::Requested => {
+                self.capture = Capture::Requested;
+                Ok(None)
+            }
+            Capture::Pending {
+                buffer,
+                fence,
+                dimensions,
+            } => {
+                fence.wait(None)?;
+                let data = buffer.read()?.to_vec();
+                Screenshot::from_raw(data, dimensions, self.format).map(Some)
+            }
+        }
+    }
+
+    fn create(
+        surface: Arc<Surface<Window>>,
+        phys: PhysicalDevice,
+        post: bool,
+    ) -> Fallible<(Self, Context)> {
+        let ctx = Context::new(
+            phys,
+            |fam| surface.is_supported(fam).unwrap_or(false),
+            &DeviceExtensions {
+                khr_swapchain: true,
+                ..DeviceExtensions::none()
+            },
+        )?;
+
+        let dpi_factor = surface.window().get_hidpi_factor();
+        let logical_size = surface
+            .window()
+            .get_inner_size()
+            .ok_or_else(|| err_msg("window no longer exists"))?;
+        let (w, h) = logical_size.to_physical(dpi_factor).into();
+
+        let caps = surface.capabilities(ctx.device().physical_device())?;
+        let format = choose_format(&caps.supported_formats)?;
+
+        let render_pass = create_render_pass(Arc::clone(ctx.device()), format, post)?;
+
+        let caps = surface.capabilities(phys)?;
+        let alpha = caps
+            .supported_composite_alpha
+            .iter()
+            .next()
+            .ok_or_else(|| err_msg("surface has no supported alpha modes"))?;
+
+        let (swapchain, images) = Swapchain::new(
+            Arc::clone(ctx.device()),
+            Arc::clone(&surface),
+            caps.min_image_count,
+            format,
+            [w, h],
+            1,
+            caps.supported_usage_flags,
+            ctx.graphics_queue(),
+            SurfaceTransform::Identity,
+            alpha,
+            PresentMode::Mailbox,
+            true,
+            None,
+        )?;
+
+        let (dbuf, scene) = create_attachments(ctx.device(), [w, h], post)?;
+        let framebuffers =
+            create_framebuffers(&render_pass, images.iter().cloned(), &dbuf, scene.as_ref())?;
+
+        Ok((
+            SwapchainTarget {
+                surface,
+                swapchain: Some(swapchain),
+                render_pass,
+                images,
+                framebuffers,
+                scene,
+                post,
+                index: None,
+                dimensions: [w, h],
+                format,
+                capture: Capture::Idle,
+                capture_supported: caps.supported_usage_flags.transfer_source,
+            },
+            ctx,
+        ))
+    }
+
+    fn swapchain(&self) -> Fallible<&Arc<Swapchain<Window>>> {
+        self.swapchain
+            .as_ref()
+            .ok_or_else(|| err_msg("swapchain was destroyed"))
+    }
+
+    fn copy_to_buffer<F>(
+        &mut self,
+        ctx: &Context,
+        fut: F,
+        index: usize,
+    ) -> Fallible<Arc<FenceSignalFuture<Box<GpuFuture + Send + Sync>>>>
+    where
+        F: GpuFuture + Send + Sync + 'static,
+    {
+        let [width, height] = self.dimensions;
+        let buffer = CpuAccessibleBuffer::from_iter(
+            Arc::clone(ctx.device()),
+            BufferUsage::transfer_destination(),
+            (0..4 * width * height).map(|_| 0u8),
+        )?;
+        let command_buffer = AutoCommandBufferBuilder::new(
+            Arc::clone(ctx.device()),
+            ctx.graphics_queue().family(),
+        )?.copy_image_to_buffer(Arc::clone(&self.images[index]), Arc::clone(&buffer))?
+            .build()?;
+
+        let fut: Box<GpuFuture + Send + Sync> =
+            Box::new(fut.then_execute(Arc::clone(ctx.graphics_queue()), command_buffer)?);
+        let fence = Arc::new(fut.then_signal_fence_and_flush()?);
+
+        self.capture = Capture::Pending {
+            buffer,
+            fence: Arc::clone(&fence),
+            dimensions: self.dimensions,
+        };
+        Ok(fence)
+    }
+}
+
+impl Target for SwapchainTarget {
+    type InitData = Window;
+
+    fn new(phys: PhysicalDevice, window: Self::InitData, post: bool) -> Fallible<(Self, Context)> {
+        let surface = vulkano_win::create_vk_surface(window, Arc::clone(phys.instance()))?;
+        SwapchainTarget::create(surface, phys, post)
+    }
+
+    fn render_pass(&self) -> &Arc<RenderPassAbstract + Send + Sync> {
+        &self.render_pass
+    }
+
+    fn scene_image(&self) -> Option<&Arc<SceneImage>> {
+        self.scene.as_ref()
+    }
+
+    fn dimensions(&self) -> [u32; 2] {
+        self.dimensions
+    }
+
+    fn hidpi_factor(&self) -> f32 {
+        self.surface.window().get_hidpi_factor() as f32
+    }
+
+    fn recreate(&mut self, ctx: &Context) -> Fallible<()> {
+        self.dimensions = self
+            .surface
+            .capabilities(ctx.device().physical_device())?
+            .current_extent
+            .unwrap();
+
+        let (swapchain, images) = self.swapchain()?.recreate_with_dimension(self.dimensions)?;
+        self.swapchain = Some(swapchain);
+        let (dbuf, scene) = create_attachments(ctx.device(), self.dimensions, self.post)?;
+        self.framebuffers = create_framebuffers(
+            &self.render_pass,
+            images.iter().cloned(),
+            &dbuf,
+            scene.as_ref(),
+        )?;
+        self.scene = scene;
+        self.images = images;
+        Ok(())
+    }
+
+    fn recover(&mut self, ctx: &Context) -> Fallible<Context> {
+        // A surface can only have one swapchain at a time, so the old one must
+        // be destroyed before a new one is created.
+        self.swapchain = None;
+        self.images.clear();
+        self.framebuffers.clear();
+        self.index = None;
+        self.capture = Capture::Idle;
+
+        let (target, ctx) = SwapchainTarget::create(
+            Arc::clone(&self.surface),
+            ctx.device().physical_device(),
+            self.post,
+        )?;
+        *self = target;
+        Ok(ctx)
+    }
+
+    fn acquire(
+        &mut self,
+        _: &Context,
+    ) -> Fallible<(
+        Arc<FramebufferAbstract + Send + Sync>,
+        Box<GpuFuture + Send + Sync>,
+    )> {
+        let (index, acquire) =
+            swapchain::acquire_next_image(Arc::clone(self.swapchain()?), None)?;
+        self.index = Some(index);
+        Ok((Arc::clone(&self.framebuffers[index]), Box::new(acquire)))
+    }
+
+    fn present<F>(&mut self, ctx: &Context, fut: F) -> Fallible<Box<GpuFuture + Send + Sync>>
+    where
+        F: GpuFuture + Send + Sync + 'static,
+    {
+        let index = self.index.take().expect("swapchain image not acquired");
+
+        if let Capture::Requested = self.capture {
+            if !self.capture_supported {
+                self.capture = Capture::Idle;
+                return Err(err_msg("swapchain images cannot be used as a transfer source"));
+            }
+
+            let fence = self.copy_to_buffer(ctx, fut, index)?;
+            return Ok(Box::new(fence.then_swapchain_present(
+                Arc::clone(ctx.graphics_queue()),
+                Arc::clone(self.swapchain()?),
+                index,
+            )));
+        }
+
+        Ok(Box::new(fut.then_swapchain_present(
+            Arc::clone(ctx.graphics_queue()),
+            Arc::clone(self.swapchain()?),
+            index,
+        )))
+    }
+}
+
+/// Prefer an 8-bit sRGB format, so that colours are gamma encoded by the
+/// hardware and look the same on every surface.
+fn choose_format(formats: &[(Format, ColorSpace)]) -> Fallible<Format> {
+    const PREFERRED: [Format; 3] = [
+        Format::B8G8R8A8Srgb,
+        Format::R8G8B8A8Srgb,
+        Format::A8B8G8R8SrgbPack32,
+    ];
+
+    let srgb = formats.iter().find(|&&(format, space)| {
+        space == ColorSpace::SrgbNonLinear && PREFERRED.contains(&format)
+    });
+    match srgb {
+        Some(&(format, _)) => Ok(format),
+        None => {
+            let &(format, _) = formats
+                .first()
+                .ok_or_else(|| err_msg("surface has no supported formats"))?;
+            warn!(
+                "Surface does not support an sRGB format, using {:?} instead.",
+                format
+            );
+            Ok(format)
+        }
+    }
+}
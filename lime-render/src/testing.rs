@@ -0,0 +1,323 @@
+//! Golden image testing.
+//!
+//! Frames are rendered offscreen through an `ImageTarget` and compared against
+//! an `expected.png` stored in a test data directory. On failure the rendered
+//! frame is written next to it as `actual.png`, along with a `diff.png`
+//! highlighting the pixels that differ. Setting the `LIME_BLESS` environment
+//! variable overwrites the expected images with the rendered frames instead.
+//!
+//! `record_frame` records the commands of a frame without rendering it, for
+//! benchmarks.
+
+use std::env;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+use failure::{err_msg, Fallible};
+use image::png::{PNGDecoder, PNGEncoder};
+use image::{ColorType, DecodingResult, ImageDecoder};
+use shrev::EventChannel;
+use specs::prelude::*;
+use vulkano::format::Format;
+use winit;
+
+use frames::FrameSlots;
+use sys::{self, Resources};
+use {
+    d2, d3, init, post, Context, ImageTarget, Layers, RenderStats, Screenshot, Target, Timings,
+    Uploads,
+};
+
+/// The environment variable that enables bless mode.
+pub const BLESS_VAR: &str = "LIME_BLESS";
+
+/// Render a single frame offscreen.
+///
+/// `setup` should add the systems named by `deps` to the dispatcher, along with
+/// any resources they need.
+pub fn render<F>(dimensions: [u32; 2], deps: &[&str], setup: F) -> Fallible<Screenshot>
+where
+    F: FnOnce(&mut World, &mut DispatcherBuilder<'static, 'static>),
+{
+    let mut world = World::new();
+    let mut dispatcher = DispatcherBuilder::new();
+    world.add_resource(EventChannel::<winit::Event>::new());
+    setup(&mut world, &mut dispatcher);
+    init::<ImageTarget>(&mut world, &mut dispatcher, dimensions, deps)?;
+    let mut dispatcher = dispatcher.build();
+
+    dispatcher.run_now(&mut world.res);
+    world.maintain();
+
+    let mut target = world.write_resource::<ImageTarget>();
+    target.read(|data, dims| Screenshot::from_raw(data.to_vec(), dims, Format::R8G8B8A8Srgb))
+}
+
+/// Record a frame, drawing everything queued on the renderers and layers of a
+/// world set up by `init` with an `ImageTarget`. The command buffer is built
+/// but never submitted, so nothing is rendered.
+pub fn record_frame(world: &World) -> Fallible<()> {
+    let mut ctx = world.write_resource::<Context>();
+    let mut target = world.write_resource::<ImageTarget>();
+    let mut frames = world.write_resource::<FrameSlots>();
+    let mut uploads = world.write_resource::<Uploads>();
+    let mut layers = world.write_resource::<Layers>();
+    let mut timings = world.write_resource::<Timings>();
+    let mut stats = world.write_resource::<RenderStats>();
+    let mut d3 = world.write_resource::<d3::Renderer>();
+    let mut debug = world.write_resource::<d3::Debug>();
+    let mut post = world.write_resource::<post::Renderer>();
+    let mut d2 = world.write_resource::<d2::Renderer>();
+    let mut res = Resources {
+        ctx: &mut ctx,
+        target: &mut target,
+        frames: &mut frames,
+        uploads: &mut uploads,
+        layers: &mut layers,
+        timings: &mut timings,
+        stats: &mut stats,
+        d3: &mut d3,
+        debug: &mut debug,
+        post: &mut post,
+        d2: &mut d2,
+    };
+
+    res.stats.begin_frame();
+    res.frames.wait()?;
+    let (fb, _) = res.target.acquire(res.ctx)?;
+    sys::record_frame(&mut res, fb)?;
+    Ok(())
+}
+
+/// Render a single frame with the given systems and compare it against the
+/// expected image in `dir`, panicking if they do not match.
+pub fn test<P, D3, D2>(dir: P, d3: D3, d2: D2, dimensions: [u32; 2])
+where
+    P: Into<PathBuf>,
+    D3: for<'a> System<'a> + Send + 'static,
+    D2: for<'a> System<'a> + Send + 'static,
+{
+    let frame = render(dimensions, &["D3", "D2"], |_, dispatcher| {
+        dispatcher.add(d3, "D3", &[]);
+        dispatcher.add(d2, "D2", &[]);
+    }).unwrap();
+    Golden::new(dir).assert(&frame);
+}
+
+/// Tolerances used when comparing a rendered frame against its expected image.
+///
+/// A pixel matches if every channel is within `channel` of the expected value,
+/// or if the perceptual difference between the two colours is at most
+/// `perceptual`. The comparison passes if no more than `pixels` pixels fail to
+/// match.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Tolerance {
+    pub channel: u8,
+    pub perceptual: f32,
+    pub pixels: usize,
+}
+
+impl Tolerance {
+    pub fn exact() -> Self {
+        Tolerance {
+            channel: 0,
+            perceptual: 0.0,
+            pixels: 0,
+        }
+    }
+
+    pub fn channel(channel: u8) -> Self {
+        Tolerance {
+            channel,
+            ..Tolerance::exact()
+        }
+    }
+
+    pub fn perceptual(perceptual: f32) -> Self {
+        Tolerance {
+            perceptual,
+            ..Tolerance::exact()
+        }
+    }
+
+    pub fn with_pixels(self, pixels: usize) -> Self {
+        Tolerance { pixels, ..self }
+    }
+
+    fn matches(&self, expected: &[u8], actual: &[u8]) -> bool {
+        let within_channel = expected
+            .iter()
+            .zip(actual)
+            .all(|(&e, &a)| (e as i16 - a as i16).abs() <= self.channel as i16);
+        within_channel || perceptual_delta(expected, actual) <= self.perceptual
+    }
+}
+
+impl Default for Tolerance {
+    fn default() -> Self {
+        Tolerance::exact()
+    }
+}
+
+/// The result of comparing two images of the same size.
+#[derive(Clone, Debug)]
+pub struct Comparison {
+    /// The number of pixels that did not match.
+    pub mismatched: usize,
+    /// An RGBA8 image highlighting mismatched pixels in red and pixels that
+    /// differ within tolerance in yellow.
+    pub diff: Vec<u8>,
+}
+
+/// Compare two RGBA8 images with the same dimensions.
+pub fn compare(expected: &[u8], actual: &[u8], tolerance: &Tolerance) -> Comparison {
+    assert_eq!(expected.len(), actual.len());
+
+    let mut mismatched = 0;
+    let mut diff = Vec::with_capacity(expected.len());
+    for (e, a) in expected.chunks(4).zip(actual.chunks(4)) {
+        if e == a {
+            let luma = (255.0 - 0.1 * (255.0 - rgb_to_y(blend_white(e)))) as u8;
+            diff.extend_from_slice(&[luma, luma, luma, 255]);
+        } else if tolerance.matches(e, a) {
+            diff.extend_from_slice(&[255, 255, 0, 255]);
+        } else {
+            mismatched += 1;
+            diff.extend_from_slice(&[255, 0, 0, 255]);
+        }
+    }
+
+    Comparison { mismatched, diff }
+}
+
+/// The perceptual difference between two RGBA8 pixels, from 0 to 1.
+///
+/// Colours are blended onto white and compared in YIQ space, weighted by the
+/// sensitivity of the eye to each component.
+pub fn perceptual_delta(lhs: &[u8], rhs: &[u8]) -> f32 {
+    const MAX_DELTA: f32 = 35215.0;
+
+    let lhs = blend_white(lhs);
+    let rhs = blend_white(rhs);
+
+    let y = rgb_to_y(lhs) - rgb_to_y(rhs);
+    let i = rgb_to_i(lhs) - rgb_to_i(rhs);
+    let q = rgb_to_q(lhs) - rgb_to_q(rhs);
+
+    (0.5053 * y * y + 0.299 * i * i + 0.1957 * q * q) / MAX_DELTA
+}
+
+fn blend_white(px: &[u8]) -> [f32; 3] {
+    let a = px[3] as f32 / 255.0;
+    let blend = |c: u8| 255.0 + (c as f32 - 255.0) * a;
+    [blend(px[0]), blend(px[1]), blend(px[2])]
+}
+
+fn rgb_to_y([r, g, b]: [f32; 3]) -> f32 {
+    r * 0.29889531 + g * 0.58662247 + b * 0.11448223
+}
+
+fn rgb_to_i([r, g, b]: [f32; 3]) -> f32 {
+    r * 0.59597799 - g * 0.27417610 - b * 0.32180189
+}
+
+fn rgb_to_q([r, g, b]: [f32; 3]) -> f32 {
+    r * 0.21147017 - g * 0.52261711 + b * 0.31114694
+}
+
+/// A directory holding the expected image for a single test.
+#[derive(Clone, Debug)]
+pub struct Golden {
+    dir: PathBuf,
+    tolerance: Tolerance,
+}
+
+impl Golden {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Golden {
+            dir: dir.into(),
+            tolerance: Tolerance::exact(),
+        }
+    }
+
+    pub fn tolerance(self, tolerance: Tolerance) -> Self {
+        Golden { tolerance, ..self }
+    }
+
+    pub fn expected_path(&self) -> PathBuf {
+        self.dir.join("expected.png")
+    }
+
+    pub fn actual_path(&self) -> PathBuf {
+        self.dir.join("actual.png")
+    }
+
+    pub fn diff_path(&self) -> PathBuf {
+        self.dir.join("diff.png")
+    }
+
+    /// Compare `frame` against the expected image, or replace the expected
+    /// image if bless mode is enabled.
+    pub fn check(&self, frame: &Screenshot) -> Fallible<()> {
+        if env::var_os(BLESS_VAR).is_some() {
+            info!("Blessing {}.", self.expected_path().display());
+            return frame.save_png(self.expected_path());
+        }
+
+        if !self.expected_path().exists() {
+            frame.save_png(self.actual_path())?;
+            return Err(err_msg(format!(
+                "{} does not exist, set {} to create it",
+                self.expected_path().display(),
+                BLESS_VAR
+            )));
+        }
+
+        let (expected, dimensions) = read_png(self.expected_path())?;
+        if dimensions != frame.dimensions() {
+            frame.save_png(self.actual_path())?;
+            return Err(err_msg(format!(
+                "expected image of size {:?}, found {:?}",
+                dimensions,
+                frame.dimensions()
+            )));
+        }
+
+        let cmp = compare(&expected, frame.data(), &self.tolerance);
+        if cmp.mismatched > self.tolerance.pixels {
+            frame.save_png(self.actual_path())?;
+            write_png(self.diff_path(), &cmp.diff, dimensions)?;
+            return Err(err_msg(format!(
+                "{} pixels differ from {} (diff written to {})",
+                cmp.mismatched,
+                self.expected_path().display(),
+                self.diff_path().display()
+            )));
+        }
+
+        Ok(())
+    }
+
+    pub fn assert(&self, frame: &Screenshot) {
+        if let Err(err) = self.check(frame) {
+            panic!("golden image test failed: {}", err);
+        }
+    }
+}
+
+fn read_png(path: impl AsRef<Path>) -> Fallible<(Vec<u8>, [u32; 2])> {
+    let mut decoder = PNGDecoder::new(File::open(path)?);
+    let (width, height) = decoder.dimensions()?;
+    if decoder.colortype()? != ColorType::RGBA(8) {
+        return Err(err_msg("expected image must be 8-bit RGBA"));
+    }
+    match decoder.read_image()? {
+        DecodingResult::U8(img) => Ok((img, [width, height])),
+        _ => Err(err_msg("expected image must be 8-bit RGBA")),
+    }
+}
+
+fn write_png(path: impl AsRef<Path>, img: &[u8], [width, height]: [u32; 2]) -> Fallible<()> {
+    PNGEncoder::new(File::create(path)?).encode(img, width, height, ColorType::RGBA(8))?;
+    Ok(())
+}
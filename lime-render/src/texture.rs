@@ -0,0 +1,63 @@
+use std::sync::Arc;
+
+use failure::{err_msg, Fallible};
+use vulkano::format::R8G8B8A8Srgb;
+use vulkano::image::{Dimensions, ImmutableImage};
+use vulkano::sync::GpuFuture;
+
+use {AssetId, Context};
+
+/// An sRGB encoded RGBA8 image stored on the GPU.
+#[derive(Clone)]
+pub struct Texture {
+    image: Arc<ImmutableImage<R8G8B8A8Srgb>>,
+    dimensions: [u32; 2],
+    asset: Option<AssetId>,
+}
+
+impl Texture {
+    /// Upload tightly packed RGBA8 rows on the transfer queue.
+    pub fn new(
+        ctx: &Context,
+        data: &[u8],
+        [width, height]: [u32; 2],
+    ) -> Fallible<(Self, Box<GpuFuture + Send + Sync>)> {
+        if data.len() != 4 * width as usize * height as usize {
+            return Err(err_msg(format!(
+                "expected {} bytes of texture data, found {}",
+                4 * width as usize * height as usize,
+                data.len()
+            )));
+        }
+
+        let (image, future) = ImmutableImage::from_iter(
+            data.iter().cloned(),
+            Dimensions::Dim2d { width, height },
+            R8G8B8A8Srgb,
+            Arc::clone(ctx.transfer_queue()),
+        )?;
+        let texture = Texture {
+            image,
+            dimensions: [width, height],
+            asset: None,
+        };
+        Ok((texture, Box::new(future)))
+    }
+
+    pub fn image(&self) -> &Arc<ImmutableImage<R8G8B8A8Srgb>> {
+        &self.image
+    }
+
+    pub fn dimensions(&self) -> [u32; 2] {
+        self.dimensions
+    }
+
+    /// The upload tracking this texture, if it was passed to `Uploads`.
+    pub fn asset(&self) -> Option<AssetId> {
+        self.asset
+    }
+
+    pub(crate) fn set_asset(&mut self, asset: AssetId) {
+        self.asset = Some(asset);
+    }
+}
@@ -0,0 +1,265 @@
+//! GPU frame times from timestamp queries.
+//!
+//! vulkano's `AutoCommandBufferBuilder` cannot write timestamps, so they are
+//! written by two small command buffers of our own, submitted to the graphics
+//! queue just before and after each frame. This times the frame as a whole,
+//! including any wait for the swapchain image, rather than individual passes.
+//! Each frame slot has its own pair of queries, which are read back when the
+//! slot is next used.
+
+use std::mem;
+use std::ptr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use failure::{err_msg, Error, Fallible};
+use vk;
+use vulkano::device::{Device, Queue};
+use vulkano::{SynchronizedVulkanObject, VulkanObject};
+
+use Context;
+
+/// The state of the queries of a frame slot.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Slot {
+    Idle,
+    Started,
+    Ended,
+}
+
+/// Timestamp queries written around each frame.
+pub(crate) struct FrameTimer {
+    device: Arc<Device>,
+    queue: Arc<Queue>,
+    query_pool: vk::QueryPool,
+    command_pool: vk::CommandPool,
+    /// The command buffers writing the start and end timestamps of each slot.
+    commands: Vec<[vk::CommandBuffer; 2]>,
+    slots: Vec<Slot>,
+    /// Nanoseconds per timestamp tick.
+    period: f64,
+    failed: bool,
+}
+
+impl FrameTimer {
+    /// Create a timer for `slots` frame slots, or `None` if the graphics queue
+    /// cannot write timestamps.
+    pub(crate) fn new(ctx: &Context, slots: usize) -> Option<Self> {
+        let limits = ctx.device().physical_device().limits();
+        if limits.timestamp_compute_and_graphics() == 0 || limits.timestamp_period() <= 0.0 {
+            info!("Timestamps are not supported, GPU frame times will not be measured.");
+            return None;
+        }
+
+        match unsafe { FrameTimer::create(ctx, slots, limits.timestamp_period()) } {
+            Ok(timer) => Some(timer),
+            Err(err) => {
+                warn!("Failed to create timestamp queries: {}.", err);
+                None
+            }
+        }
+    }
+
+    unsafe fn create(ctx: &Context, slots: usize, period: f32) -> Fallible<Self> {
+        // Handles are destroyed by `drop` even if creation fails part way.
+        let mut timer = FrameTimer {
+            device: Arc::clone(ctx.device()),
+            queue: Arc::clone(ctx.graphics_queue()),
+            query_pool: 0,
+            command_pool: 0,
+            commands: Vec::with_capacity(slots),
+            slots: vec![Slot::Idle; slots],
+            period: period as f64,
+            failed: false,
+        };
+        let vk = timer.device.pointers();
+        let device = timer.device.internal_object();
+
+        let info = vk::QueryPoolCreateInfo {
+            sType: vk::STRUCTURE_TYPE_QUERY_POOL_CREATE_INFO,
+            pNext: ptr::null(),
+            flags: 0,
+            queryType: vk::QUERY_TYPE_TIMESTAMP,
+            queryCount: 2 * slots as u32,
+            pipelineStatistics: 0,
+        };
+        check(vk.CreateQueryPool(device, &info, ptr::null(), &mut timer.query_pool))?;
+
+        let info = vk::CommandPoolCreateInfo {
+            sType: vk::STRUCTURE_TYPE_COMMAND_POOL_CREATE_INFO,
+            pNext: ptr::null(),
+            flags: 0,
+            queueFamilyIndex: timer.queue.family().id(),
+        };
+        check(vk.CreateCommandPool(device, &info, ptr::null(), &mut timer.command_pool))?;
+
+        let mut buffers = vec![0; 2 * slots];
+        let info = vk::CommandBufferAllocateInfo {
+            sType: vk::STRUCTURE_TYPE_COMMAND_BUFFER_ALLOCATE_INFO,
+            pNext: ptr::null(),
+            commandPool: timer.command_pool,
+            level: vk::COMMAND_BUFFER_LEVEL_PRIMARY,
+            commandBufferCount: buffers.len() as u32,
+        };
+        check(vk.AllocateCommandBuffers(device, &info, buffers.as_mut_ptr()))?;
+
+        for (slot, pair) in buffers.chunks(2).enumerate() {
+            let query = 2 * slot as u32;
+
+            begin(vk, pair[0])?;
+            vk.CmdResetQueryPool(pair[0], timer.query_pool, query, 2);
+            vk.CmdWriteTimestamp(
+                pair[0],
+                vk::PIPELINE_STAGE_TOP_OF_PIPE_BIT,
+                timer.query_pool,
+                query,
+            );
+            check(vk.EndCommandBuffer(pair[0]))?;
+
+            begin(vk, pair[1])?;
+            vk.CmdWriteTimestamp(
+                pair[1],
+                vk::PIPELINE_STAGE_BOTTOM_OF_PIPE_BIT,
+                timer.query_pool,
+                query + 1,
+            );
+            check(vk.EndCommandBuffer(pair[1]))?;
+
+            timer.commands.push([pair[0], pair[1]]);
+        }
+
+        Ok(timer)
+    }
+
+    /// Read back the time taken by the last frame rendered with `slot`, which
+    /// must have finished. This must be called before the slot is started
+    /// again.
+    pub(crate) fn take(&mut self, slot: usize) -> Option<Duration> {
+        let state = mem::replace(&mut self.slots[slot], Slot::Idle);
+        if self.failed {
+            return None;
+        }
+
+        // A slot that was started but not ended is waited on, since its command
+        // buffer cannot be submitted again until it has finished.
+        let count = match state {
+            Slot::Idle => return None,
+            Slot::Started => 1,
+            Slot::Ended => 2,
+        };
+        match unsafe { self.results(slot, count) } {
+            Ok([start, end]) if state == Slot::Ended => {
+                let ticks = end.wrapping_sub(start);
+                Some(Duration::from_nanos((ticks as f64 * self.period) as u64))
+            }
+            Ok(_) => None,
+            Err(err) => {
+                self.fail(err);
+                None
+            }
+        }
+    }
+
+    /// Write the start timestamp of the frame rendered with `slot`. This must be
+    /// called before the frame is submitted.
+    pub(crate) fn start(&mut self, slot: usize) {
+        if self.submit(slot, 0) {
+            self.slots[slot] = Slot::Started;
+        }
+    }
+
+    /// Write the end timestamp of the frame rendered with `slot`. This must be
+    /// called after the frame is submitted.
+    pub(crate) fn end(&mut self, slot: usize) {
+        if self.slots[slot] == Slot::Started && self.submit(slot, 1) {
+            self.slots[slot] = Slot::Ended;
+        }
+    }
+
+    /// Submit one of the command buffers of `slot`, returning whether it was
+    /// submitted.
+    fn submit(&mut self, slot: usize, index: usize) -> bool {
+        if self.failed {
+            return false;
+        }
+
+        let command_buffer = self.commands[slot][index];
+        let info = vk::SubmitInfo {
+            sType: vk::STRUCTURE_TYPE_SUBMIT_INFO,
+            pNext: ptr::null(),
+            waitSemaphoreCount: 0,
+            pWaitSemaphores: ptr::null(),
+            pWaitDstStageMask: ptr::null(),
+            commandBufferCount: 1,
+            pCommandBuffers: &command_buffer,
+            signalSemaphoreCount: 0,
+            pSignalSemaphores: ptr::null(),
+        };
+        let result = unsafe {
+            let queue = self.queue.internal_object_guard();
+            check(self.device.pointers().QueueSubmit(*queue, 1, &info, 0))
+        };
+        match result {
+            Ok(()) => true,
+            Err(err) => {
+                self.fail(err);
+                false
+            }
+        }
+    }
+
+    /// Wait for the first `count` timestamps of `slot` and read them.
+    unsafe fn results(&self, slot: usize, count: u32) -> Fallible<[u64; 2]> {
+        let mut data = [0u64; 2];
+        check(self.device.pointers().GetQueryPoolResults(
+            self.device.internal_object(),
+            self.query_pool,
+            2 * slot as u32,
+            count,
+            count as usize * mem::size_of::<u64>(),
+            data.as_mut_ptr() as *mut _,
+            mem::size_of::<u64>() as u64,
+            vk::QUERY_RESULT_64_BIT | vk::QUERY_RESULT_WAIT_BIT,
+        ))?;
+        Ok(data)
+    }
+
+    fn fail(&mut self, err: Error) {
+        warn!("GPU frame timing failed, disabling it: {}.", err);
+        self.failed = true;
+    }
+}
+
+impl Drop for FrameTimer {
+    fn drop(&mut self) {
+        // Wait for submitted command buffers before destroying them.
+        for slot in 0..self.slots.len() {
+            self.take(slot);
+        }
+
+        let vk = self.device.pointers();
+        let device = self.device.internal_object();
+        unsafe {
+            vk.DestroyCommandPool(device, self.command_pool, ptr::null());
+            vk.DestroyQueryPool(device, self.query_pool, ptr::null());
+        }
+    }
+}
+
+unsafe fn begin(vk: &vk::DevicePointers, command_buffer: vk::CommandBuffer) -> Fallible<()> {
+    let info = vk::CommandBufferBeginInfo {
+        sType: vk::STRUCTURE_TYPE_COMMAND_BUFFER_BEGIN_INFO,
+        pNext: ptr::null(),
+        flags: 0,
+        pInheritanceInfo: ptr::null(),
+    };
+    check(vk.BeginCommandBuffer(command_buffer, &info))
+}
+
+fn check(result: vk::Result) -> Fallible<()> {
+    if result == vk::SUCCESS {
+        Ok(())
+    } else {
+        Err(err_msg(format!("Vulkan error {}", result)))
+    }
+}
@@ -0,0 +1,106 @@
+//! Per-pass render timings.
+//!
+//! When enabled, the render system measures how long each built-in renderer and
+//! custom layer takes to record its commands on the CPU, and publishes the
+//! results of the last frame in the `Timings` resource.
+//!
+//! The GPU time of whole frames is measured with timestamp queries where the
+//! device supports them. Passes are not timed individually on the GPU, since
+//! timestamps cannot be written inside a render pass recorded with vulkano's
+//! `AutoCommandBufferBuilder`.
+
+use std::time::{Duration, Instant};
+
+/// The time taken by one pass of the last frame.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PassTiming {
+    pub name: String,
+    /// The time taken to record the pass's commands.
+    pub cpu: Duration,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Timings {
+    enabled: bool,
+    passes: Vec<PassTiming>,
+    frame: Duration,
+    gpu_frame: Option<Duration>,
+}
+
+impl Timings {
+    pub(crate) fn new() -> Self {
+        Timings::default()
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    /// Enable or disable timing. Timing is disabled by default.
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+        if !enabled {
+            self.passes.clear();
+            self.frame = Duration::default();
+            self.gpu_frame = None;
+        }
+    }
+
+    /// The passes of the last frame, in the order they were drawn.
+    pub fn passes(&self) -> &[PassTiming] {
+        &self.passes
+    }
+
+    /// Find a pass by name. The built-in passes are named `d3`, `post` and
+    /// `d2`, and layers are named by `Layer::name`.
+    pub fn pass(&self, name: &str) -> Option<&PassTiming> {
+        self.passes.iter().find(|pass| pass.name == name)
+    }
+
+    /// The CPU time taken to record and submit the last frame.
+    pub fn frame(&self) -> Duration {
+        self.frame
+    }
+
+    /// The time taken by the GPU to execute a recent frame. Results are read
+    /// back once a frame has finished, so this lags a few frames behind. It is
+    /// `None` if the device does not support timestamps, or until the first
+    /// result is available.
+    pub fn gpu_frame(&self) -> Option<Duration> {
+        self.gpu_frame
+    }
+
+    pub(crate) fn set_gpu_frame(&mut self, gpu_frame: Duration) {
+        self.gpu_frame = Some(gpu_frame);
+    }
+
+    pub(crate) fn begin_frame(&mut self) -> Option<Instant> {
+        self.passes.clear();
+        self.start()
+    }
+
+    pub(crate) fn end_frame(&mut self, start: Option<Instant>) {
+        if let Some(start) = start {
+            self.frame = start.elapsed();
+        }
+    }
+
+    /// Start timing a pass, if timing is enabled.
+    pub(crate) fn start(&self) -> Option<Instant> {
+        if self.enabled {
+            Some(Instant::now())
+        } else {
+            None
+        }
+    }
+
+    /// Record a pass started with `start`.
+    pub(crate) fn record(&mut self, name: &str, start: Option<Instant>) {
+        if let Some(start) = start {
+            self.passes.push(PassTiming {
+                name: name.to_owned(),
+                cpu: start.elapsed(),
+            });
+        }
+    }
+}
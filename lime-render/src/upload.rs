@@ -0,0 +1,199 @@
+//! Tracking of resources uploaded on the transfer queue.
+//!
+//! Uploads are submitted as soon as they are tracked, and every frame waits for
+//! uploads that have not finished yet, so a tracked resource is always safe to
+//! use. Renderers check `Uploads::is_ready` to skip resources that would
+//! otherwise stall the frame.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use failure::{err_msg, Fallible};
+use vulkano::sync::{FenceSignalFuture, GpuFuture};
+
+use d3::Mesh;
+use Texture;
+
+/// Identifies a single upload tracked by `Uploads`.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct AssetId(u64);
+
+type Fence = Arc<FenceSignalFuture<Box<GpuFuture + Send + Sync>>>;
+
+struct Pending {
+    asset: AssetId,
+    fence: Fence,
+}
+
+#[derive(Default)]
+pub struct Uploads {
+    next: u64,
+    pending: Vec<Pending>,
+}
+
+impl Uploads {
+    pub(crate) fn new() -> Self {
+        Uploads::default()
+    }
+
+    /// Submit an upload and track its progress.
+    pub fn track(&mut self, future: impl GpuFuture + Send + Sync + 'static) -> Fallible<AssetId> {
+        let future: Box<GpuFuture + Send + Sync> = Box::new(future);
+        let fence = Arc::new(future.then_signal_fence_and_flush()?);
+
+        let asset = AssetId(self.next);
+        self.next += 1;
+        self.pending.push(Pending { asset, fence });
+        Ok(asset)
+    }
+
+    /// Track the upload of a mesh, such as one returned by `MeshBuilder::build`.
+    pub fn track_mesh<F>(&mut self, (mut mesh, future): (Mesh, F)) -> Fallible<Mesh>
+    where
+        F: GpuFuture + Send + Sync + 'static,
+    {
+        mesh.set_asset(self.track(future)?);
+        Ok(mesh)
+    }
+
+    /// Track the upload of several meshes sharing a future, such as those
+    /// returned by `d3::load_obj`.
+    pub fn track_meshes<F>(&mut self, (mut meshes, future): (Vec<Mesh>, F)) -> Fallible<Vec<Mesh>>
+    where
+        F: GpuFuture + Send + Sync + 'static,
+    {
+        let asset = self.track(future)?;
+        for mesh in &mut meshes {
+            mesh.set_asset(asset);
+        }
+        Ok(meshes)
+    }
+
+    pub fn track_texture<F>(&mut self, (mut texture, future): (Texture, F)) -> Fallible<Texture>
+    where
+        F: GpuFuture + Send + Sync + 'static,
+    {
+        texture.set_asset(self.track(future)?);
+        Ok(texture)
+    }
+
+    /// Whether an upload has finished.
+    pub fn is_ready(&self, asset: AssetId) -> bool {
+        self.pending
+            .iter()
+            .find(|pending| pending.asset == asset)
+            .map_or(true, |pending| is_signaled(&pending.fence))
+    }
+
+    /// Block until an upload has finished.
+    pub fn wait(&self, asset: AssetId) -> Fallible<()> {
+        match self.pending.iter().find(|pending| pending.asset == asset) {
+            Some(pending) => Ok(pending.fence.wait(None)?),
+            None if asset.0 < self.next => Ok(()),
+            None => Err(err_msg("unknown asset")),
+        }
+    }
+
+    /// The number of uploads that have not finished.
+    pub fn pending(&self) -> usize {
+        self.pending
+            .iter()
+            .filter(|pending| !is_signaled(&pending.fence))
+            .count()
+    }
+
+    /// Forget about finished uploads.
+    pub(crate) fn poll(&mut self) {
+        self.pending.retain(|pending| !is_signaled(&pending.fence));
+    }
+
+    /// Make a frame wait for every unfinished upload.
+    pub(crate) fn join(
+        &self,
+        future: Box<GpuFuture + Send + Sync>,
+    ) -> Box<GpuFuture + Send + Sync> {
+        self.pending.iter().fold(future, |future, pending| {
+            Box::new(future.join(Arc::clone(&pending.fence)))
+        })
+    }
+
+    /// Forget about every upload after the device has been lost.
+    pub(crate) fn reset(&mut self) {
+        self.pending.clear();
+    }
+}
+
+fn is_signaled(fence: &Fence) -> bool {
+    fence.wait(Some(Duration::from_secs(0))).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use vulkano::sync::now;
+
+    use super::*;
+    use d3::MeshBuilder;
+    use Context;
+
+    fn upload(ctx: &Context) -> Box<GpuFuture + Send + Sync> {
+        MeshBuilder::cube(1.0).build(ctx).1
+    }
+
+    #[test]
+    fn track() {
+        let ctx = Context::for_tests();
+        let mut uploads = Uploads::new();
+        let a = uploads.track(upload(&ctx)).unwrap();
+        let b = uploads.track(upload(&ctx)).unwrap();
+        assert_ne!(a, b);
+
+        uploads.wait(a).unwrap();
+        uploads.wait(b).unwrap();
+        assert!(uploads.is_ready(a));
+        assert!(uploads.is_ready(b));
+        assert_eq!(uploads.pending(), 0);
+        assert!(uploads.wait(AssetId(2)).is_err());
+    }
+
+    #[test]
+    fn poll() {
+        let ctx = Context::for_tests();
+        let mut uploads = Uploads::new();
+        let asset = uploads.track(upload(&ctx)).unwrap();
+        uploads.wait(asset).unwrap();
+        assert_eq!(uploads.pending.len(), 1);
+
+        // Finished uploads are forgotten, but are still ready.
+        uploads.poll();
+        assert!(uploads.pending.is_empty());
+        assert!(uploads.is_ready(asset));
+        uploads.wait(asset).unwrap();
+    }
+
+    #[test]
+    fn join() {
+        let ctx = Context::for_tests();
+        let mut uploads = Uploads::new();
+        let asset = uploads.track(upload(&ctx)).unwrap();
+
+        let frame: Box<GpuFuture + Send + Sync> = Box::new(now(Arc::clone(ctx.device())));
+        let frame = uploads.join(frame).then_signal_fence_and_flush().unwrap();
+        frame.wait(None).unwrap();
+        uploads.wait(asset).unwrap();
+    }
+
+    #[test]
+    fn reset() {
+        let ctx = Context::for_tests();
+        let mut uploads = Uploads::new();
+        let old = uploads.track(upload(&ctx)).unwrap();
+        uploads.reset();
+        assert_eq!(uploads.pending(), 0);
+        assert!(uploads.is_ready(old));
+
+        // Assets tracked after a reset are not confused with older ones.
+        let new = uploads.track(upload(&ctx)).unwrap();
+        assert_ne!(old, new);
+        uploads.wait(new).unwrap();
+    }
+}
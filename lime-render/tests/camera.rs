@@ -0,0 +1,107 @@
+extern crate lime_render as render;
+
+use render::d2::{Camera2d, Point};
+use render::d3::{Aabb, Camera, Matrix, Sphere, Vector};
+
+#[test]
+fn project() {
+    let camera = Camera::new(Vector(0.0, 0.0, 5.0), Vector::origin());
+    let view_proj = camera.proj(1.0) * camera.view();
+
+    let center = view_proj.transform_point(Vector::origin());
+    assert!(center.0.abs() < 1e-6 && center.1.abs() < 1e-6);
+    assert!(0.0 < center.2 && center.2 < 1.0);
+
+    let near = view_proj.transform_point(Vector(0.0, 0.0, 5.0 - camera.near));
+    assert!(near.2.abs() < 1e-4);
+
+    let up = view_proj.transform_point(Vector(0.0, 1.0, 0.0));
+    assert!(up.1 < 0.0);
+}
+
+#[test]
+fn cull() {
+    let camera = Camera::new(Vector(0.0, 0.0, 5.0), Vector::origin());
+    let frustum = camera.frustum(16.0 / 9.0);
+    let sphere = |x, y, z, radius| Sphere {
+        center: Vector(x, y, z),
+        radius,
+    };
+
+    assert!(frustum.intersects_sphere(&sphere(0.0, 0.0, 0.0, 1.0)));
+    assert!(!frustum.intersects_sphere(&sphere(0.0, 0.0, 10.0, 1.0)));
+    assert!(!frustum.intersects_sphere(&sphere(100.0, 0.0, 0.0, 1.0)));
+    assert!(frustum.intersects_sphere(&sphere(0.0, 0.0, 10.0, 6.0)));
+    assert!(!frustum.intersects_sphere(&sphere(0.0, 0.0, -2000.0, 1.0)));
+}
+
+#[test]
+fn bounds() {
+    let points = [
+        Vector(-1.0, 0.0, 0.0),
+        Vector(1.0, 2.0, 0.0),
+        Vector(0.0, 0.0, 4.0),
+    ];
+    let aabb = Aabb::from_points(points.iter().cloned());
+    assert_eq!(aabb.min, Vector(-1.0, 0.0, 0.0));
+    assert_eq!(aabb.max, Vector(1.0, 2.0, 4.0));
+    assert!(Aabb::empty().is_empty());
+
+    let sphere = Sphere::from_points(&points);
+    assert_eq!(sphere.center, Vector(0.0, 1.0, 2.0));
+    for &point in &points {
+        assert!((point - sphere.center).length() <= sphere.radius + 1e-6);
+    }
+
+    let moved = sphere.transform(
+        &(Matrix::translation(Vector(1.0, 0.0, 0.0)) * Matrix::scale(Vector(2.0, 1.0, 1.0))),
+    );
+    assert_eq!(moved.center, Vector(1.0, 1.0, 2.0));
+    assert_eq!(moved.radius, 2.0 * sphere.radius);
+}
+
+#[test]
+fn inverse() {
+    let matrix = Matrix::translation(Vector(1.0, -2.0, 3.0))
+        * Matrix::rotation(Vector(0.0, 1.0, 1.0), 1.2)
+        * Matrix::scale(Vector(2.0, 0.5, 3.0));
+    let point = Vector(4.0, 5.0, -6.0);
+    let inverse = matrix.inverse().unwrap();
+    let back = inverse.transform_point(matrix.transform_point(point));
+    assert!((back - point).length() < 1e-4);
+
+    let product = matrix * inverse;
+    for col in 0..4 {
+        for row in 0..4 {
+            let expected = if col == row { 1.0 } else { 0.0 };
+            assert!((product.0[col][row] - expected).abs() < 1e-5);
+        }
+    }
+
+    assert_eq!(Matrix::scale(Vector(1.0, 0.0, 1.0)).inverse(), None);
+}
+
+#[test]
+fn camera_2d() {
+    let size = [800.0, 600.0];
+    let mut camera = Camera2d::new(Point(50.0, -20.0));
+    assert_eq!(
+        camera.world_to_screen(Point(50.0, -20.0), size),
+        Point(400.0, 300.0)
+    );
+
+    camera.zoom = 4.0;
+    assert_eq!(
+        camera.world_to_screen(Point(60.0, -20.0), size),
+        Point(440.0, 300.0)
+    );
+
+    camera.rotation = 0.5;
+    let point = Point(123.0, 45.0);
+    let screen = camera.world_to_screen(point, size);
+    let world = camera.screen_to_world(screen, size);
+    assert!((world.0 - point.0).abs() < 1e-3 && (world.1 - point.1).abs() < 1e-3);
+
+    camera.zoom = 0.0;
+    assert_eq!(camera.screen_to_world(screen, size), camera.position);
+}
@@ -0,0 +1,127 @@
+extern crate env_logger;
+extern crate image;
+extern crate lime_render as render;
+extern crate shrev;
+extern crate specs;
+extern crate winit;
+
+use std::env;
+use std::fs::{self, File};
+use std::path::PathBuf;
+use std::process;
+
+use image::png::PNGDecoder;
+use image::ImageDecoder;
+use render::{d2, CaptureFormat, CaptureOptions, Color, Context, ImageTarget};
+use shrev::EventChannel;
+use specs::prelude::*;
+
+const DIMENSIONS: [u32; 2] = [32, 16];
+const FRAMES: u64 = 5;
+
+/// Draws a rectangle that moves one pixel to the right every frame.
+struct Draw {
+    frame: u32,
+}
+
+impl<'a> System<'a> for Draw {
+    type SystemData = WriteExpect<'a, d2::Renderer>;
+
+    fn run(&mut self, mut d2: Self::SystemData) {
+        let x = self.frame as f32;
+        d2.draw_rect(d2::Point(x, 0.0), d2::Point(x + 4.0, 4.0), Color::RED);
+        self.frame += 1;
+    }
+}
+
+fn setup() -> (World, Dispatcher<'static, 'static>) {
+    env_logger::try_init().ok();
+
+    let mut world = World::new();
+    let mut dispatcher = DispatcherBuilder::new().with(Draw { frame: 0 }, "Draw", &[]);
+    world.add_resource(EventChannel::<winit::Event>::new());
+    render::init::<ImageTarget>(&mut world, &mut dispatcher, DIMENSIONS, &["Draw"]).unwrap();
+    (world, dispatcher.build())
+}
+
+/// A path in the temporary directory that is unique to this test process.
+fn temp_path(name: &str) -> PathBuf {
+    env::temp_dir().join(format!("lime-capture-{}-{}", process::id(), name))
+}
+
+#[test]
+fn png() {
+    let (mut world, mut dispatcher) = setup();
+    let dir = temp_path("png");
+    let options = CaptureOptions::new(&dir, CaptureFormat::Png, FRAMES);
+    render::capture_frames(&mut world, &mut dispatcher, &options, |_, _| ()).unwrap();
+
+    let mut files: Vec<_> = fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .collect();
+    files.sort();
+    let expected: Vec<_> = (0..FRAMES)
+        .map(|index| format!("frame_{:05}.png", index))
+        .collect();
+    assert_eq!(files, expected);
+
+    for file in &files {
+        let mut decoder = PNGDecoder::new(File::open(dir.join(file)).unwrap());
+        let (width, height) = decoder.dimensions().unwrap();
+        assert_eq!([width, height], DIMENSIONS);
+    }
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn raw() {
+    let (mut world, mut dispatcher) = setup();
+    let path = temp_path("raw");
+    let options = CaptureOptions::new(&path, CaptureFormat::Raw, FRAMES);
+    render::capture_frames(&mut world, &mut dispatcher, &options, |_, _| ()).unwrap();
+
+    let [width, height] = DIMENSIONS;
+    let len = fs::metadata(&path).unwrap().len();
+    assert_eq!(len, FRAMES * 4 * width as u64 * height as u64);
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn ring_full() {
+    let (mut world, mut dispatcher) = setup();
+    {
+        let ctx = world.read_resource::<Context>();
+        let mut target = world.write_resource::<ImageTarget>();
+        target.start_capture(&ctx, 2).unwrap();
+    }
+
+    // Render more frames than the ring holds without taking any.
+    for _ in 0..FRAMES {
+        dispatcher.dispatch(&world.res);
+        world.maintain();
+    }
+
+    let mut target = world.write_resource::<ImageTarget>();
+    assert_eq!(target.frames_captured(), Some(FRAMES));
+
+    let mut frames = Vec::new();
+    target
+        .take_captured(0, |index, frame| {
+            frames.push((index, frame));
+            Ok(())
+        }).unwrap();
+
+    let indices: Vec<u64> = frames.iter().map(|&(index, _)| index).collect();
+    assert_eq!(indices, (0..FRAMES).collect::<Vec<_>>());
+    for (index, frame) in frames {
+        assert_eq!(frame.dimensions(), DIMENSIONS);
+        // The rectangle starts at the frame number, so each frame differs.
+        let pixel = 4 * index as usize;
+        assert_eq!(frame.data()[pixel..pixel + 4], [255, 0, 0, 255]);
+        if index > 0 {
+            let before = pixel - 4;
+            assert_eq!(frame.data()[before..before + 4], [0, 0, 0, 255]);
+        }
+    }
+}
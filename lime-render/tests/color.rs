@@ -0,0 +1,100 @@
+extern crate lime_render as render;
+
+use render::{Color, ParseError};
+
+#[test]
+fn hex() {
+    assert_eq!("#FF0000".parse::<Color>().unwrap(), Color::RED);
+    assert_eq!("#00ff00ff".parse::<Color>().unwrap(), Color::GREEN);
+    assert_eq!("#00F".parse::<Color>().unwrap(), Color::BLUE);
+    assert_eq!("#0000".parse::<Color>().unwrap(), Color::TRANSPARENT);
+    assert_eq!(
+        "#123".parse::<Color>().unwrap(),
+        "#112233".parse::<Color>().unwrap()
+    );
+
+    assert!("#12345".parse::<Color>().is_err());
+    assert!("#GG0000".parse::<Color>().is_err());
+
+    match "FF0000".parse::<Color>() {
+        Err(ParseError::MissingHash) => (),
+        res => panic!("unexpected result {:?}", res),
+    }
+}
+
+#[test]
+fn functions() {
+    assert_eq!("rgb(255, 0, 0)".parse::<Color>().unwrap(), Color::RED);
+    assert_eq!(
+        "rgba(0, 100%, 0, 0.5)".parse::<Color>().unwrap(),
+        Color::rgba(0.0, 1.0, 0.0, 0.5)
+    );
+    assert_eq!(
+        "RGB(128,128,128)".parse::<Color>().unwrap(),
+        "#808080".parse::<Color>().unwrap()
+    );
+
+    assert!("rgb(0, 0)".parse::<Color>().is_err());
+    assert!("rgba(0, 0, 0)".parse::<Color>().is_err());
+    assert!("rgb(0, 0, x)".parse::<Color>().is_err());
+}
+
+#[test]
+fn names() {
+    assert_eq!("white".parse::<Color>().unwrap(), Color::WHITE);
+    assert_eq!("Blue".parse::<Color>().unwrap(), Color::BLUE);
+    assert_eq!(Color::named("transparent"), Some(Color::TRANSPARENT));
+    assert_eq!(
+        Color::named("rebeccapurple"),
+        Some("#663399".parse().unwrap())
+    );
+    assert_eq!(Color::named("notacolor"), None);
+    match "notacolor".parse::<Color>() {
+        Err(ParseError::UnknownName) => (),
+        res => panic!("unexpected result {:?}", res),
+    }
+}
+
+#[test]
+fn srgb() {
+    let grey = "#808080".parse::<Color>().unwrap();
+    assert!((grey.r - 0.2158605).abs() < 1e-5);
+    assert_eq!(grey.to_srgb8(), [128, 128, 128, 255]);
+
+    for c in 0..=255 {
+        assert_eq!(Color::srgb8([c, c, c, c]).to_srgb8(), [c, c, c, c]);
+    }
+}
+
+#[test]
+fn hsl() {
+    assert_eq!(Color::hsl(0.0, 1.0, 0.5), Color::RED);
+    assert_eq!(Color::hsl(120.0, 1.0, 0.5), Color::GREEN);
+    assert_eq!(Color::hsl(-120.0, 1.0, 0.5), Color::BLUE);
+    assert_eq!(Color::hsl(0.0, 0.0, 1.0), Color::WHITE);
+    assert_eq!(Color::hsv(240.0, 1.0, 1.0), Color::BLUE);
+    assert_eq!(Color::hsv(0.0, 0.0, 0.0), Color::BLACK);
+    assert_eq!(
+        Color::hsv(30.0, 1.0, 1.0).to_srgb8(),
+        Color::hsl(30.0, 1.0, 0.5).to_srgb8()
+    );
+}
+
+#[test]
+fn display() {
+    assert_eq!(Color::RED.to_string(), "#FF0000");
+    assert_eq!(Color::rgba(0.0, 0.0, 1.0, 0.5).to_string(), "#0000FF80");
+
+    let color = "#12345678".parse::<Color>().unwrap();
+    assert_eq!(color.to_string().parse::<Color>().unwrap(), color);
+}
+
+#[test]
+fn premultiplied() {
+    assert_eq!(Color::RED.premultiplied(), Color::RED);
+    assert_eq!(Color::TRANSPARENT.premultiplied(), Color::TRANSPARENT);
+    assert_eq!(
+        Color::rgba(1.0, 0.5, 0.0, 0.5).premultiplied(),
+        Color::rgba(0.5, 0.25, 0.0, 0.5)
+    );
+}
@@ -1,15 +1,12 @@
 extern crate env_logger;
-extern crate image;
 extern crate lime_render as render;
-extern crate shrev;
 extern crate specs;
-extern crate winit;
 
 mod common;
 
 use std::path::Path;
 
-use render::{d2, Color};
+use render::{d2, testing, Color};
 use specs::prelude::*;
 
 struct D3;
@@ -20,20 +17,24 @@ impl<'a> System<'a> for D3 {
     fn run(&mut self, (): Self::SystemData) {}
 }
 
-fn test_d2(name: impl AsRef<Path>, dims: [u32; 2], f: impl FnMut(&mut d2::Renderer) + Send) {
-    struct D2<F>(F);
+struct D2<F>(F);
 
-    impl<'a, F> System<'a> for D2<F>
-    where
-        F: FnMut(&mut d2::Renderer) + Send,
-    {
-        type SystemData = WriteExpect<'a, d2::Renderer>;
+impl<'a, F> System<'a> for D2<F>
+where
+    F: FnMut(&mut d2::Renderer) + Send + 'static,
+{
+    type SystemData = WriteExpect<'a, d2::Renderer>;
 
-        fn run(&mut self, mut r: Self::SystemData) {
-            (self.0)(&mut r)
-        }
+    fn run(&mut self, mut r: Self::SystemData) {
+        (self.0)(&mut r)
     }
+}
 
+fn test_d2(
+    name: impl AsRef<Path>,
+    dims: [u32; 2],
+    f: impl FnMut(&mut d2::Renderer) + Send + 'static,
+) {
     common::test(Path::new("d2").join(name), D3, D2(f), dims);
 }
 
@@ -48,3 +49,34 @@ fn triangle() {
         r.draw_tris(&VERTICES, Color::RED)
     })
 }
+
+#[test]
+fn blend() {
+    use d2::{BlendMode, Point};
+
+    let color: Color = "#FF000080".parse().unwrap();
+    // Half-transparent red over black, with the alpha of the colour in linear
+    // space.
+    let expected = Color::rgb(color.a, 0.0, 0.0).to_srgb8();
+    for &(mode, queued) in &[
+        (BlendMode::Alpha, color),
+        (BlendMode::Premultiplied, color.premultiplied()),
+    ] {
+        let frame = testing::render([16, 16], &["D2"], move |_, dispatcher| {
+            let draw = move |r: &mut d2::Renderer| {
+                r.with_blend_mode(mode, |r| {
+                    r.draw_rect(Point(0.0, 0.0), Point(16.0, 16.0), queued)
+                })
+            };
+            dispatcher.add(D2(draw), "D2", &[]);
+        }).unwrap();
+
+        for pixel in frame.data().chunks(4) {
+            let close = pixel
+                .iter()
+                .zip(&expected)
+                .all(|(&actual, &expected)| (actual as i32 - expected as i32).abs() <= 1);
+            assert!(close, "{:?}: {:?} != {:?}", mode, pixel, expected);
+        }
+    }
+}
@@ -0,0 +1,34 @@
+extern crate env_logger;
+extern crate lime_render as render;
+extern crate specs;
+
+use render::d3::{self, MeshBuilder};
+use render::{testing, Context};
+use specs::prelude::*;
+
+struct Batching;
+
+impl<'a> System<'a> for Batching {
+    type SystemData = (ReadExpect<'a, Context>, WriteExpect<'a, d3::Renderer>);
+
+    fn run(&mut self, (ctx, mut renderer): Self::SystemData) {
+        let (cube, _) = MeshBuilder::cube(1.0).build(&ctx);
+        let (other, _) = MeshBuilder::cube(1.0).build(&ctx);
+
+        renderer.draw_mesh(cube.clone());
+        renderer.draw_mesh(other.clone());
+        renderer.draw_mesh(cube);
+        assert_eq!(renderer.batches(), 2);
+        renderer.draw_mesh(other);
+        assert_eq!(renderer.batches(), 2);
+    }
+}
+
+#[test]
+fn batching() {
+    env_logger::try_init().ok();
+
+    testing::render([64, 64], &["Batching"], |_, dispatcher| {
+        dispatcher.add(Batching, "Batching", &[]);
+    }).unwrap();
+}
@@ -0,0 +1,43 @@
+extern crate lime_render as render;
+
+use std::time::Duration;
+
+use render::d3::{Aabb, Debug, Vector};
+use render::Color;
+
+#[test]
+fn shapes() {
+    let mut debug = Debug::new();
+    let aabb = Aabb {
+        min: Vector(-1.0, -1.0, -1.0),
+        max: Vector(1.0, 1.0, 1.0),
+    };
+    debug.wire_box(&aabb, Color::WHITE);
+    assert_eq!(debug.lines().len(), 12);
+    for line in debug.lines() {
+        assert_eq!((line.end - line.start).length(), 2.0);
+    }
+
+    debug.clear();
+    debug.arrow(Vector::origin(), Vector(0.0, 0.0, 1.0), Color::WHITE);
+    assert_eq!(debug.lines().len(), 5);
+
+    debug.clear();
+    debug.grid(Vector::origin(), 2.0, 2, Color::WHITE);
+    assert_eq!(debug.lines().len(), 6);
+}
+
+#[test]
+fn scoped_state() {
+    let mut debug = Debug::new();
+    debug.with_depth_test(false, |debug| {
+        debug.with_duration(Duration::from_secs(1), |debug| {
+            debug.line(Vector::origin(), Vector(1.0, 0.0, 0.0), Color::WHITE);
+        });
+    });
+    debug.line(Vector::origin(), Vector(0.0, 1.0, 0.0), Color::WHITE);
+
+    let lines = debug.lines();
+    assert!(!lines[0].depth_test);
+    assert!(lines[1].depth_test);
+}
@@ -0,0 +1,117 @@
+extern crate lime_render as render;
+
+use render::d3::{MeshBuilder, Vector};
+
+#[test]
+fn cube() {
+    let cube = MeshBuilder::cube(2.0);
+    assert_eq!(cube.positions().len(), 24);
+    assert_eq!(cube.indices().len(), 36);
+    assert!(!cube.needs_u32_indices());
+    for &pos in cube.positions() {
+        assert_eq!(pos.0.abs(), 1.0);
+        assert_eq!(pos.1.abs(), 1.0);
+        assert_eq!(pos.2.abs(), 1.0);
+    }
+
+    let mut smooth = cube.clone();
+    smooth.smooth_normals();
+    assert_eq!(smooth.normals(), cube.normals());
+}
+
+#[test]
+fn weld() {
+    let mut builder = MeshBuilder::new();
+    let up = Vector(0.0, 0.0, 1.0);
+    let a = builder.vertex(Vector(0.0, 0.0, 0.0), up, [0.0, 0.0]);
+    let b = builder.vertex(Vector(1.0, 0.0, 0.0), up, [0.0, 0.0]);
+    let c = builder.vertex(Vector(0.0, 1.0, 0.0), up, [0.0, 0.0]);
+    let d = builder.vertex(Vector(1.0, 0.0001, 0.0), up, [0.0, 0.0]);
+    let e = builder.vertex(Vector(1.0, 1.0, 0.0), up, [0.0, 0.0]);
+    builder.vertex(Vector(5.0, 5.0, 5.0), up, [0.0, 0.0]);
+    builder.triangle(a, b, c).triangle(d, e, c);
+
+    builder.weld(0.001);
+    assert_eq!(builder.positions().len(), 4);
+    assert_eq!(builder.indices(), &[0, 1, 2, 1, 3, 2]);
+}
+
+#[test]
+fn weld_across_cells() {
+    let mut builder = MeshBuilder::new();
+    let up = Vector(0.0, 0.0, 1.0);
+    let a = builder.vertex(Vector(0.0004, 0.0, 0.0), up, [0.0, 0.0]);
+    let b = builder.vertex(Vector(0.0006, 0.0, 0.0), up, [0.0, 0.0]);
+    let c = builder.vertex(Vector(0.0, 1.0, 0.0), up, [0.0, 0.0]);
+    let d = builder.vertex(Vector(0.0, 1.002, 0.0), up, [0.0, 0.0]);
+    builder.triangle(a, b, c).triangle(a, b, d);
+
+    builder.weld(0.001);
+    assert_eq!(builder.positions().len(), 3);
+    assert_eq!(builder.indices(), &[0, 0, 1, 0, 0, 2]);
+}
+
+#[test]
+fn normals() {
+    let mut plane = MeshBuilder::plane(1.0, 1.0);
+    plane.smooth_normals();
+    for &normal in plane.normals() {
+        assert_eq!(normal, Vector(0.0, 1.0, 0.0));
+    }
+
+    let mut sphere = MeshBuilder::uv_sphere(1.0, 16, 8);
+    sphere.flat_normals();
+    assert_eq!(sphere.positions().len(), sphere.indices().len());
+    for tri in sphere.indices().chunks(3) {
+        let normal = sphere.normals()[tri[0] as usize];
+        let center = sphere.positions()[tri[0] as usize];
+        assert!(normal.dot(center) > 0.0);
+    }
+}
+
+#[test]
+fn cylinder() {
+    let cylinder = MeshBuilder::cylinder(1.0, 2.0, 12);
+    assert_eq!(cylinder.positions().len(), 2 * 13 + 2 * 13);
+    assert_eq!(cylinder.indices().len(), 3 * (2 * 12 + 2 * 12));
+}
+
+#[test]
+fn large_mesh() {
+    let mut builder = MeshBuilder::new();
+    let up = Vector(0.0, 0.0, 1.0);
+    for i in 0..70_000 {
+        builder.vertex(Vector(i as f32, 0.0, 0.0), up, [0.0, 0.0]);
+    }
+    assert!(builder.needs_u32_indices());
+}
+
+#[test]
+fn weld_earliest() {
+    let mut builder = MeshBuilder::new();
+    let up = Vector(0.0, 0.0, 1.0);
+    // The first two are too far apart to merge, and end up in different cells,
+    // while the third is close to both.
+    let a = builder.vertex(Vector(0.0011, 0.0, 0.0), up, [0.0, 0.0]);
+    let b = builder.vertex(Vector(0.0, 0.0, 0.0), up, [0.0, 0.0]);
+    let c = builder.vertex(Vector(0.0006, 0.0, 0.0), up, [0.0, 0.0]);
+    builder.triangle(a, b, c);
+
+    builder.weld(0.001);
+    assert_eq!(builder.positions().len(), 2);
+    assert_eq!(builder.indices(), &[0, 1, 0]);
+}
+
+#[test]
+fn weld_signed_zero() {
+    let mut builder = MeshBuilder::new();
+    let up = Vector(0.0, 0.0, 1.0);
+    let a = builder.vertex(Vector(0.0, 0.0, 0.0), up, [0.0, 0.0]);
+    let b = builder.vertex(Vector(-0.0, 0.0, -0.0), up, [0.0, 0.0]);
+    let c = builder.vertex(Vector(1.0, 0.0, 0.0), up, [0.0, 0.0]);
+    builder.triangle(a, b, c);
+
+    builder.weld(0.0);
+    assert_eq!(builder.positions().len(), 2);
+    assert_eq!(builder.indices(), &[0, 0, 1]);
+}
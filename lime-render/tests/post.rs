@@ -0,0 +1,48 @@
+extern crate env_logger;
+extern crate lime_render as render;
+extern crate specs;
+
+use render::d3::{self, InstanceData, MeshBuilder};
+use render::post::PostProcessing;
+use render::testing::{self, Tolerance};
+use render::{d2, Color, Context, Screenshot};
+use specs::prelude::*;
+
+/// Draws a cube and a rectangle over it.
+struct Draw;
+
+impl<'a> System<'a> for Draw {
+    type SystemData = (
+        ReadExpect<'a, Context>,
+        WriteExpect<'a, d3::Renderer>,
+        WriteExpect<'a, d2::Renderer>,
+    );
+
+    fn run(&mut self, (ctx, mut d3, mut d2): Self::SystemData) {
+        let (cube, _) = MeshBuilder::cube(1.0).build(&ctx);
+        d3.draw_mesh_instanced(cube, &[InstanceData::identity(Color::RED)]);
+        d2.draw_rect(d2::Point(0.0, 0.0), d2::Point(16.0, 16.0), Color::BLUE);
+    }
+}
+
+fn render(post: bool) -> Screenshot {
+    testing::render([64, 64], &["Draw"], |world, dispatcher| {
+        world.add_resource(PostProcessing(post));
+        dispatcher.add(Draw, "Draw", &[]);
+    }).unwrap()
+}
+
+#[test]
+fn disabled() {
+    env_logger::try_init().ok();
+
+    let enabled = render(true);
+    let disabled = render(false);
+    assert!(enabled.data().chunks(4).any(|px| px[..3] != [0, 0, 0]));
+
+    // The default settings leave the scene unchanged, so skipping the stage
+    // only differs by the rounding of the HDR scene image.
+    let cmp = testing::compare(enabled.data(), disabled.data(), &Tolerance::channel(1));
+    assert_eq!(cmp.mismatched, 0);
+}
+
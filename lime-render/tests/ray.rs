@@ -0,0 +1,106 @@
+extern crate lime_render as render;
+
+use render::d2::Point;
+use render::d3::{Aabb, Camera, Matrix, Ray, Sphere, Vector};
+
+#[test]
+fn camera_ray() {
+    let camera = Camera::new(Vector(0.0, 0.0, 5.0), Vector::origin());
+    let size = [800.0, 600.0];
+
+    let ray = camera.ray(Point(400.0, 300.0), size);
+    assert_eq!(ray.origin, camera.eye);
+    assert_eq!(ray.dir, Vector(0.0, 0.0, -1.0));
+
+    let view_proj = camera.proj(size[0] / size[1]) * camera.view();
+    let target = Vector(1.0, 0.5, -2.0);
+    let ndc = view_proj.transform_point(target);
+    let point = Point((ndc.0 + 1.0) * size[0] / 2.0, (ndc.1 + 1.0) * size[1] / 2.0);
+    let ray = camera.ray(point, size);
+    let to_target = (target - ray.origin).normalize();
+    assert!(ray.dir.dot(to_target) > 0.9999);
+}
+
+#[test]
+fn bounds() {
+    let ray = Ray::new(Vector(0.0, 0.0, 5.0), Vector(0.0, 0.0, -2.0));
+    assert_eq!(ray.dir, Vector(0.0, 0.0, -1.0));
+
+    let aabb = Aabb {
+        min: Vector(-1.0, -1.0, -1.0),
+        max: Vector(1.0, 1.0, 1.0),
+    };
+    assert_eq!(ray.intersect_aabb(&aabb), Some(4.0));
+    let moved = aabb.transform(&Matrix::translation(Vector(5.0, 0.0, 0.0)));
+    assert_eq!(ray.intersect_aabb(&moved), None);
+    let inside = Ray::new(Vector::origin(), Vector(1.0, 0.0, 0.0));
+    assert_eq!(inside.intersect_aabb(&aabb), Some(0.0));
+
+    let sphere = Sphere {
+        center: Vector(0.0, 0.0, -1.0),
+        radius: 2.0,
+    };
+    assert_eq!(ray.intersect_sphere(&sphere), Some(4.0));
+    let behind = Ray::new(Vector(0.0, 0.0, 5.0), Vector(0.0, 0.0, 1.0));
+    assert_eq!(behind.intersect_sphere(&sphere), None);
+}
+
+#[test]
+fn triangles() {
+    let positions = [
+        Vector(-1.0, -1.0, 0.0),
+        Vector(1.0, -1.0, 0.0),
+        Vector(-1.0, 1.0, 0.0),
+        Vector(1.0, 1.0, 0.0),
+    ];
+    let indices = [0, 1, 2, 2, 1, 3];
+
+    let ray = Ray::new(Vector(0.5, 0.5, 5.0), Vector(0.0, 0.0, -1.0));
+    let hit = ray
+        .intersect_triangles(&positions, &indices, &Matrix::identity())
+        .unwrap();
+    assert_eq!(hit.distance, 5.0);
+    assert_eq!(hit.point, Vector(0.5, 0.5, 0.0));
+    assert_eq!(hit.triangle, Some(1));
+
+    let near = Matrix::translation(Vector(0.0, 0.0, 2.0));
+    let hit = ray.intersect_triangles(&positions, &indices, &near).unwrap();
+    assert_eq!(hit.distance, 3.0);
+
+    let miss = Ray::new(Vector(2.0, 0.0, 5.0), Vector(0.0, 0.0, -1.0));
+    assert_eq!(
+        miss.intersect_triangles(&positions, &indices, &Matrix::identity()),
+        None
+    );
+}
+
+#[test]
+fn transformed_triangles() {
+    let positions = [
+        Vector(-1.0, -1.0, 0.0),
+        Vector(1.0, -1.0, 0.0),
+        Vector(-1.0, 1.0, 0.0),
+    ];
+    let indices = [0, 1, 2];
+    let transform = Matrix::translation(Vector(0.0, 0.0, -3.0))
+        * Matrix::rotation(Vector(1.0, 1.0, 0.0), 0.3)
+        * Matrix::scale(Vector(2.0, 2.0, 2.0));
+
+    // The same triangle moved into world space.
+    let world = [
+        transform.transform_point(positions[0]),
+        transform.transform_point(positions[1]),
+        transform.transform_point(positions[2]),
+    ];
+    let ray = Ray::new(Vector(-0.5, -0.5, 5.0), Vector(0.05, 0.0, -1.0));
+    let expected = ray.intersect_triangle(world).unwrap();
+
+    let hit = ray
+        .intersect_triangles(&positions, &indices, &transform)
+        .unwrap();
+    assert!((hit.distance - expected).abs() < 1e-4);
+    assert!((hit.point - ray.at(expected)).length() < 1e-4);
+
+    let flat = Matrix::scale(Vector(1.0, 1.0, 0.0));
+    assert_eq!(ray.intersect_triangles(&positions, &indices, &flat), None);
+}
@@ -0,0 +1,74 @@
+extern crate env_logger;
+extern crate lime_render as render;
+extern crate shrev;
+extern crate specs;
+extern crate winit;
+
+use render::d3::{self, InstanceData, MeshBuilder};
+use render::{d2, Color, Context, ImageTarget, RenderStats};
+use shrev::EventChannel;
+use specs::prelude::*;
+
+const INSTANCES: usize = 3;
+
+/// Draws three instances of a cube and a rectangle on the first frame only.
+struct Draw {
+    frame: usize,
+}
+
+impl<'a> System<'a> for Draw {
+    type SystemData = (
+        ReadExpect<'a, Context>,
+        WriteExpect<'a, d3::Renderer>,
+        WriteExpect<'a, d2::Renderer>,
+    );
+
+    fn run(&mut self, (ctx, mut d3, mut d2): Self::SystemData) {
+        self.frame += 1;
+        if self.frame > 1 {
+            return;
+        }
+
+        let (cube, _) = MeshBuilder::cube(1.0).build(&ctx);
+        let instances = [InstanceData::identity(Color::RED); INSTANCES];
+        d3.draw_mesh_instanced(cube, &instances);
+        d2.draw_rect(d2::Point(0.0, 0.0), d2::Point(10.0, 10.0), Color::BLUE);
+    }
+}
+
+#[test]
+fn counters() {
+    env_logger::try_init().ok();
+
+    let mut world = World::new();
+    let mut dispatcher = DispatcherBuilder::new().with(Draw { frame: 0 }, "Draw", &[]);
+    world.add_resource(EventChannel::<winit::Event>::new());
+    render::init::<ImageTarget>(&mut world, &mut dispatcher, [64, 64], &["Draw"]).unwrap();
+    let mut dispatcher = dispatcher.build();
+
+    let cube = MeshBuilder::cube(1.0);
+    let (cube_vertices, cube_indices) = (cube.positions().len(), cube.indices().len());
+
+    dispatcher.run_now(&mut world.res);
+    world.maintain();
+    {
+        let stats = world.read_resource::<RenderStats>();
+        // The cube, post-processing and the rectangle.
+        assert_eq!(stats.draw_calls, 3);
+        assert_eq!(stats.meshes, INSTANCES);
+        assert_eq!(stats.vertices, INSTANCES * cube_vertices + 3 + 4);
+        assert_eq!(stats.indices, INSTANCES * cube_indices + 6);
+        assert_eq!(stats.sections, 1);
+        assert_eq!(stats.glyphs, 0);
+    }
+
+    // Nothing is drawn on the second frame, so only post-processing counts.
+    dispatcher.run_now(&mut world.res);
+    world.maintain();
+    let stats = world.read_resource::<RenderStats>();
+    assert_eq!(stats.draw_calls, 1);
+    assert_eq!(stats.meshes, 0);
+    assert_eq!(stats.vertices, 3);
+    assert_eq!(stats.indices, 0);
+    assert_eq!(stats.sections, 0);
+}
@@ -0,0 +1,40 @@
+extern crate lime_render as render;
+
+use render::testing::{compare, perceptual_delta, Tolerance};
+
+static EXPECTED: [u8; 8] = [255, 0, 0, 255, 0, 0, 0, 255];
+
+#[test]
+fn exact() {
+    let cmp = compare(&EXPECTED, &EXPECTED, &Tolerance::exact());
+    assert_eq!(cmp.mismatched, 0);
+    assert_eq!(cmp.diff.len(), EXPECTED.len());
+
+    let actual = [254, 0, 0, 255, 0, 0, 0, 255];
+    assert_eq!(compare(&EXPECTED, &actual, &Tolerance::exact()).mismatched, 1);
+}
+
+#[test]
+fn channel() {
+    let actual = [250, 0, 0, 255, 0, 3, 0, 255];
+    assert_eq!(compare(&EXPECTED, &actual, &Tolerance::channel(3)).mismatched, 1);
+    assert_eq!(compare(&EXPECTED, &actual, &Tolerance::channel(5)).mismatched, 0);
+    assert_eq!(
+        compare(&EXPECTED, &actual, &Tolerance::channel(3).with_pixels(1)).mismatched,
+        1
+    );
+}
+
+#[test]
+fn perceptual() {
+    assert_eq!(perceptual_delta(&[0, 0, 0, 255], &[0, 0, 0, 255]), 0.0);
+    assert!(perceptual_delta(&[0, 0, 0, 255], &[255, 255, 255, 255]) > 0.9);
+    assert_eq!(perceptual_delta(&[0, 0, 0, 0], &[255, 0, 0, 0]), 0.0);
+
+    let actual = [255, 8, 8, 255, 0, 0, 0, 255];
+    assert_eq!(compare(&EXPECTED, &actual, &Tolerance::exact()).mismatched, 1);
+    assert_eq!(
+        compare(&EXPECTED, &actual, &Tolerance::perceptual(0.01)).mismatched,
+        0
+    );
+}
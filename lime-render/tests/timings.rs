@@ -0,0 +1,46 @@
+extern crate env_logger;
+extern crate lime_render as render;
+extern crate shrev;
+extern crate specs;
+extern crate winit;
+
+use std::time::Duration;
+
+use render::{ImageTarget, Timings};
+use shrev::EventChannel;
+use specs::prelude::*;
+
+#[test]
+fn timings() {
+    env_logger::try_init().ok();
+
+    let mut world = World::new();
+    let mut dispatcher = DispatcherBuilder::new();
+    world.add_resource(EventChannel::<winit::Event>::new());
+    render::init::<ImageTarget>(&mut world, &mut dispatcher, [64, 64], &[]).unwrap();
+    let mut dispatcher = dispatcher.build();
+    world.write_resource::<Timings>().set_enabled(true);
+
+    // Timestamps are read back once the frame slot comes round again, which
+    // takes more frames than there are slots.
+    for _ in 0..4 {
+        dispatcher.dispatch(&world.res);
+        world.maintain();
+    }
+
+    {
+        let timings = world.read_resource::<Timings>();
+        let names: Vec<&str> = timings.passes().iter().map(|pass| &pass.name[..]).collect();
+        assert_eq!(names, ["d3", "post", "d2"]);
+        assert!(timings.frame() > Duration::default());
+        // Not every device supports timestamps.
+        if let Some(gpu_frame) = timings.gpu_frame() {
+            assert!(gpu_frame < Duration::from_secs(10));
+        }
+    }
+
+    world.write_resource::<Timings>().set_enabled(false);
+    let timings = world.read_resource::<Timings>();
+    assert!(timings.passes().is_empty());
+    assert_eq!(timings.gpu_frame(), None);
+}
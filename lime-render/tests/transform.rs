@@ -0,0 +1,27 @@
+extern crate lime_render as render;
+
+use std::f32::consts::FRAC_PI_2;
+
+use render::d2::{Point, Transform};
+
+#[test]
+fn transform_point() {
+    let point = Point(10.0, 20.0);
+    assert_eq!(Transform::identity().transform_point(point), point);
+    assert_eq!(
+        Transform::translation(5.0, -5.0).transform_point(point),
+        Point(15.0, 15.0)
+    );
+    assert_eq!(
+        Transform::rotation(FRAC_PI_2).transform_point(Point(1.0, 0.0)),
+        Point(0.0, 1.0)
+    );
+
+    let combined = Transform::translation(100.0, 0.0) * Transform::scale(2.0, 3.0);
+    assert_eq!(combined.transform_point(point), Point(120.0, 60.0));
+    assert_eq!(
+        combined.transform_point(point),
+        Transform::translation(100.0, 0.0)
+            .transform_point(Transform::scale(2.0, 3.0).transform_point(point))
+    );
+}
@@ -37,9 +37,8 @@ pub fn init(events_loop: &EventsLoop) -> (World, Dispatcher<'static, 'static>) {
         &mut world,
         &mut dispatcher,
         window,
-        D3::NAME,
-        DrawSystem::NAME,
-    );
+        &[D3::NAME, DrawSystem::NAME],
+    ).unwrap_or_else(throw);
     ui::init(&mut world, &mut dispatcher);
 
     (world, dispatcher.build())
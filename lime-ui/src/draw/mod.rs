@@ -1,3 +1,5 @@
+#[cfg(test)]
+mod tests;
 mod style;
 
 pub use self::style::{Style, StyleEvent};
@@ -48,7 +50,7 @@ impl<'a> System<'a> for DrawSystem {
                     if let Some(brush) = brushes.get(ent) {
                         match *brush {
                             Brush::Color(color) => if let Some(pos) = poss.get(ent) {
-                                renderer.draw_tris(&pos.tris(), color)
+                                renderer.draw_rect(pos.top_left(), pos.bottom_right(), color)
                             },
                         }
                     }
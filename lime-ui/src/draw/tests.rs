@@ -0,0 +1,46 @@
+use std::path::Path;
+
+use cassowary::strength::REQUIRED;
+use cassowary::WeightedRelation::EQ;
+use render::testing::{self, Golden};
+use render::Color;
+use specs::prelude::*;
+
+use super::*;
+use layout::Position;
+use tree::{Node, Root};
+
+fn create_rect(world: &mut World, [left, top, width, height]: [f64; 4], color: Color) {
+    let root = world.read_resource::<Root>().entity();
+    let pos = Position::new();
+    let cons = pos.constraints_builder()
+        .with(pos.left_var() | EQ(REQUIRED) | left)
+        .with(pos.top_var() | EQ(REQUIRED) | top)
+        .with(pos.width_var() | EQ(REQUIRED) | width)
+        .with(pos.height_var() | EQ(REQUIRED) | height)
+        .build();
+
+    Node::with_parent(world.create_entity(), root)
+        .with(pos)
+        .with(cons)
+        .with(Brush::Color(color))
+        .build();
+}
+
+#[test]
+fn rects() {
+    let frame = testing::render([200, 150], &[DrawSystem::NAME], |world, dispatcher| {
+        // Lay out the tree before the first frame, since the layout system runs
+        // after the draw system.
+        let mut layout = DispatcherBuilder::new();
+        ::init(world, &mut layout);
+        create_rect(world, [20.0, 30.0, 100.0, 60.0], Color::RED);
+        create_rect(world, [80.0, 50.0, 100.0, 80.0], Color::BLUE);
+        layout.build().dispatch(&world.res);
+
+        dispatcher.add(DrawSystem, DrawSystem::NAME, &[]);
+    }).unwrap();
+
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/data/draw/rects");
+    Golden::new(dir).assert(&frame);
+}
@@ -32,6 +32,12 @@ impl MouseFocus {
     pub fn entity(&self) -> Option<Entity> {
         self.entity
     }
+
+    /// The last cursor position, in logical pixels from the top left of the
+    /// window. This can be passed to `Camera::ray` for picking.
+    pub fn point(&self) -> Point {
+        self.point
+    }
 }
 
 impl MouseEvent {
@@ -108,6 +108,10 @@ fn assert_mouse_focus(
     }
 
     assert_eq!(world.read_resource::<MouseFocus>().entity(), new);
+    assert_eq!(
+        world.read_resource::<MouseFocus>().point(),
+        Point(x as f32, y as f32)
+    );
 }
 
 fn mouse_moved(world: &mut World, reader: &mut ReaderId<Event>) -> bool {